@@ -0,0 +1,319 @@
+use std::collections::HashMap;
+
+const PROGRAM_START: usize = 0x200;
+const MEMORY_SIZE: usize = 4096;
+
+// a tiny two pass assembler for the mnemonics in cowgod's chip-8 reference,
+// e.g. `loop: DRW V0, V1, 5` or `JP loop`. `;` starts a comment, labels end
+// in `:`, numbers can be decimal, #hex, 0xhex, $hex, %binary or 0bbinary,
+// and DB/DW emit raw bytes/words
+pub fn assemble(source: &str) -> Result<Vec<u8>, String> {
+    let mut labels: HashMap<String, u16> = HashMap::new();
+    let mut statements: Vec<Statement> = Vec::new();
+    let mut address = PROGRAM_START;
+
+    for (index, raw_line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let mut text = raw_line.split(';').next().unwrap_or("").trim();
+
+        while let Some(colon) = text.find(':') {
+            let label = text[..colon].trim();
+            if !is_valid_label(label) {
+                return Err(format!("line {}: invalid label '{}'", line_number, label));
+            }
+            if is_reserved_name(label) {
+                return Err(format!(
+                    "line {}: '{}' is an operand name and can't be a label",
+                    line_number, label
+                ));
+            }
+            if labels.insert(label.to_string(), address as u16).is_some() {
+                return Err(format!("line {}: duplicate label '{}'", line_number, label));
+            }
+            text = text[colon + 1..].trim();
+        }
+
+        if text.is_empty() {
+            continue;
+        }
+
+        let (mnemonic, rest) = match text.split_once(char::is_whitespace) {
+            Some((mnemonic, rest)) => (mnemonic, rest.trim()),
+            None => (text, ""),
+        };
+        let operands: Vec<String> = if rest.is_empty() {
+            Vec::new()
+        } else {
+            rest.split(',').map(|op| op.trim().to_string()).collect()
+        };
+        let statement = Statement {
+            line_number,
+            mnemonic: mnemonic.to_uppercase(),
+            operands,
+        };
+
+        address += statement.size();
+        if address > MEMORY_SIZE {
+            return Err(format!(
+                "line {}: program does not fit in memory",
+                line_number
+            ));
+        }
+        statements.push(statement);
+    }
+
+    let mut output = Vec::new();
+    for statement in statements {
+        let bytes = statement
+            .encode(&labels)
+            .map_err(|e| format!("line {}: {}", statement.line_number, e))?;
+        output.extend(bytes);
+    }
+
+    Ok(output)
+}
+
+struct Statement {
+    line_number: usize,
+    mnemonic: String,
+    operands: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Operand {
+    Register(u16),
+    Value(u16),
+    I,
+    IndirectI,
+    DelayTimer,
+    SoundTimer,
+    Key,
+    Font,
+    Bcd,
+}
+
+impl Statement {
+    fn size(&self) -> usize {
+        match self.mnemonic.as_str() {
+            "DB" => self.operands.len(),
+            "DW" => self.operands.len() * 2,
+            _ => 2,
+        }
+    }
+
+    fn encode(&self, labels: &HashMap<String, u16>) -> Result<Vec<u8>, String> {
+        let operands = self
+            .operands
+            .iter()
+            .map(|op| parse_operand(op, labels))
+            .collect::<Result<Vec<Operand>, String>>()?;
+
+        match self.mnemonic.as_str() {
+            "DB" => {
+                return operands
+                    .iter()
+                    .map(|op| match op {
+                        Operand::Value(value) => Ok(byte(*value)? as u8),
+                        _ => Err(String::from("DB expects numbers")),
+                    })
+                    .collect();
+            }
+            "DW" => {
+                let mut bytes = Vec::new();
+                for op in operands {
+                    match op {
+                        Operand::Value(value) => bytes.extend(value.to_be_bytes()),
+                        _ => return Err(String::from("DW expects numbers")),
+                    }
+                }
+                return Ok(bytes);
+            }
+            _ => {}
+        }
+
+        use Operand::*;
+        let instruction = match (self.mnemonic.as_str(), operands.as_slice()) {
+            ("CLS", []) => 0x00E0,
+            ("RET", []) => 0x00EE,
+            ("SYS", [Value(a)]) => address(*a)?,
+            ("JP", [Value(a)]) => 0x1000 | address(*a)?,
+            ("JP", [Register(0), Value(a)]) => 0xB000 | address(*a)?,
+            ("CALL", [Value(a)]) => 0x2000 | address(*a)?,
+            ("SE", [Register(x), Value(b)]) => 0x3000 | x << 8 | byte(*b)?,
+            ("SE", [Register(x), Register(y)]) => 0x5000 | x << 8 | y << 4,
+            ("SNE", [Register(x), Value(b)]) => 0x4000 | x << 8 | byte(*b)?,
+            ("SNE", [Register(x), Register(y)]) => 0x9000 | x << 8 | y << 4,
+            ("LD", [Register(x), Value(b)]) => 0x6000 | x << 8 | byte(*b)?,
+            ("LD", [Register(x), Register(y)]) => 0x8000 | x << 8 | y << 4,
+            ("LD", [I, Value(a)]) => 0xA000 | address(*a)?,
+            ("LD", [Register(x), DelayTimer]) => 0xF007 | x << 8,
+            ("LD", [Register(x), Key]) => 0xF00A | x << 8,
+            ("LD", [DelayTimer, Register(x)]) => 0xF015 | x << 8,
+            ("LD", [SoundTimer, Register(x)]) => 0xF018 | x << 8,
+            ("LD", [Font, Register(x)]) => 0xF029 | x << 8,
+            ("LD", [Bcd, Register(x)]) => 0xF033 | x << 8,
+            ("LD", [IndirectI, Register(x)]) => 0xF055 | x << 8,
+            ("LD", [Register(x), IndirectI]) => 0xF065 | x << 8,
+            ("ADD", [Register(x), Value(b)]) => 0x7000 | x << 8 | byte(*b)?,
+            ("ADD", [Register(x), Register(y)]) => 0x8004 | x << 8 | y << 4,
+            ("ADD", [I, Register(x)]) => 0xF01E | x << 8,
+            ("OR", [Register(x), Register(y)]) => 0x8001 | x << 8 | y << 4,
+            ("AND", [Register(x), Register(y)]) => 0x8002 | x << 8 | y << 4,
+            ("XOR", [Register(x), Register(y)]) => 0x8003 | x << 8 | y << 4,
+            ("SUB", [Register(x), Register(y)]) => 0x8005 | x << 8 | y << 4,
+            ("SHR", [Register(x)]) => 0x8006 | x << 8 | x << 4,
+            ("SHR", [Register(x), Register(y)]) => 0x8006 | x << 8 | y << 4,
+            ("SUBN", [Register(x), Register(y)]) => 0x8007 | x << 8 | y << 4,
+            ("SHL", [Register(x)]) => 0x800E | x << 8 | x << 4,
+            ("SHL", [Register(x), Register(y)]) => 0x800E | x << 8 | y << 4,
+            ("RND", [Register(x), Value(b)]) => 0xC000 | x << 8 | byte(*b)?,
+            ("DRW", [Register(x), Register(y), Value(n)]) => 0xD000 | x << 8 | y << 4 | nibble(*n)?,
+            ("SKP", [Register(x)]) => 0xE09E | x << 8,
+            ("SKNP", [Register(x)]) => 0xE0A1 | x << 8,
+            _ => {
+                return Err(format!(
+                    "unknown instruction or bad operands: {} {}",
+                    self.mnemonic,
+                    self.operands.join(", ")
+                ))
+            }
+        };
+
+        Ok(instruction.to_be_bytes().to_vec())
+    }
+}
+
+fn parse_operand(operand: &str, labels: &HashMap<String, u16>) -> Result<Operand, String> {
+    let upper = operand.to_uppercase();
+    match upper.as_str() {
+        "I" => return Ok(Operand::I),
+        "[I]" => return Ok(Operand::IndirectI),
+        "DT" => return Ok(Operand::DelayTimer),
+        "ST" => return Ok(Operand::SoundTimer),
+        "K" => return Ok(Operand::Key),
+        "F" => return Ok(Operand::Font),
+        "B" => return Ok(Operand::Bcd),
+        _ => {}
+    }
+
+    if upper.len() == 2 && upper.starts_with('V') {
+        if let Ok(register) = u16::from_str_radix(&upper[1..], 16) {
+            return Ok(Operand::Register(register));
+        }
+    }
+
+    if let Some(number) = parse_number(operand) {
+        return Ok(Operand::Value(number?));
+    }
+
+    labels
+        .get(operand)
+        .map(|address| Operand::Value(*address))
+        .ok_or_else(|| format!("unknown label '{}'", operand))
+}
+
+// None means "this isn't a number", Some(Err) means "this is a broken number"
+fn parse_number(text: &str) -> Option<Result<u16, String>> {
+    let (digits, radix) = if let Some(hex) = text
+        .strip_prefix('#')
+        .or_else(|| text.strip_prefix('$'))
+        .or_else(|| text.strip_prefix("0x"))
+        .or_else(|| text.strip_prefix("0X"))
+    {
+        (hex, 16)
+    } else if let Some(binary) = text
+        .strip_prefix('%')
+        .or_else(|| text.strip_prefix("0b"))
+        .or_else(|| text.strip_prefix("0B"))
+    {
+        (binary, 2)
+    } else if text.starts_with(|c: char| c.is_ascii_digit()) {
+        (text, 10)
+    } else {
+        return None;
+    };
+
+    Some(u16::from_str_radix(digits, radix).map_err(|_| format!("invalid number '{}'", text)))
+}
+
+fn is_valid_label(label: &str) -> bool {
+    label.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// registers and the other operands parse_operand knows, in any case. a label
+// called one of these could never be used
+fn is_reserved_name(label: &str) -> bool {
+    let upper = label.to_uppercase();
+    matches!(upper.as_str(), "I" | "DT" | "ST" | "K" | "F" | "B")
+        || (upper.len() == 2
+            && upper.starts_with('V')
+            && upper[1..].chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+fn address(value: u16) -> Result<u16, String> {
+    if value > 0x0FFF {
+        return Err(format!("address #{:X} does not fit in 12 bits", value));
+    }
+    Ok(value)
+}
+
+fn byte(value: u16) -> Result<u16, String> {
+    if value > 0xFF {
+        return Err(format!("value #{:X} does not fit in a byte", value));
+    }
+    Ok(value)
+}
+
+fn nibble(value: u16) -> Result<u16, String> {
+    if value > 0xF {
+        return Err(format!("value #{:X} does not fit in 4 bits", value));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::disassembler::disassemble;
+
+    #[test]
+    fn assemble_labels_and_data_test() {
+        let source = "
+            start:  LD V0, #0A   ; x
+                    LD I, sprite
+                    DRW V0, V1, 2
+                    JP start
+            sprite: DB %11110000, $90
+        ";
+
+        let rom = assemble(source).unwrap();
+
+        assert_eq!(
+            vec![0x60, 0x0A, 0xA2, 0x08, 0xD0, 0x12, 0x12, 0x00, 0xF0, 0x90],
+            rom
+        );
+    }
+
+    #[test]
+    fn disassembly_round_trip_test() {
+        let rom = std::fs::read("roms/2-ibm-logo.ch8").unwrap();
+        let listing = disassemble(&rom, 0x200).join("\n");
+
+        assert_eq!(rom, assemble(&listing).unwrap());
+    }
+
+    #[test]
+    fn assemble_error_points_at_line_test() {
+        let error = assemble("CLS\nLD V0, #100\n").unwrap_err();
+
+        assert_eq!("line 2: value #100 does not fit in a byte", error);
+        assert_eq!(
+            "line 1: 'vA' is an operand name and can't be a label",
+            assemble("vA: CLS\n").unwrap_err()
+        );
+        assert!(assemble("dt: CLS\n").is_err());
+        assert!(assemble("VG: CLS\n").is_ok());
+    }
+}
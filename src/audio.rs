@@ -1,54 +1,55 @@
-use rodio::{source::Source, OutputStream, Sink};
+use crate::beeper::Beeper;
+use crate::frontend::AudioSink;
+use crate::settings::Settings;
+use rodio::{OutputStream, Sink};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 pub struct AudioDriver {
     // found out i needed to keep this stream alive the hard way.
     // hours lost: 3
-    stream: OutputStream,
-    sink: Sink,
-    samples: Vec<f32>,
-    sample_rate: u32,
+    _stream: OutputStream,
+    _sink: Sink,
+    tone_on: Arc<AtomicBool>,
 }
 
 impl AudioDriver {
-    pub fn build() -> AudioDriver {
-        let (stream, stream_handle) = OutputStream::try_default().unwrap();
-        let sink = Sink::try_new(&stream_handle).unwrap();
-
-        // had to look this one up!
-        // this makes a beep
-        let freq = 440.0;
-        let sample_rate = 44100;
-        let duration_secs = 1;
-        let samples = Self::generate_square_wave(freq, duration_secs, sample_rate);
-
-        AudioDriver {
-            stream,
-            sink,
-            samples,
-            sample_rate,
-        }
+    pub fn build(settings: &Settings) -> Result<AudioDriver, String> {
+        let (stream, stream_handle) = OutputStream::try_default().map_err(|e| e.to_string())?;
+        let sink = Sink::try_new(&stream_handle).map_err(|e| e.to_string())?;
+
+        // the beeper plays forever, play and stop just flip the flag it listens to
+        let tone_on = Arc::new(AtomicBool::new(false));
+        sink.append(Beeper::new(
+            tone_on.clone(),
+            settings.audio_frequency as f32,
+            settings.volume as f32 / 100.0,
+            settings.waveform,
+            44100,
+        ));
+
+        Ok(AudioDriver {
+            _stream: stream,
+            _sink: sink,
+            tone_on,
+        })
     }
 
     pub fn play(&mut self) {
-        let source = rodio::buffer::SamplesBuffer::new(1, self.sample_rate, self.samples.clone());
-        self.sink.append(source.convert_samples::<f32>());
+        self.tone_on.store(true, Ordering::Relaxed);
     }
 
     pub fn stop(&mut self) {
-        self.sink.stop();
+        self.tone_on.store(false, Ordering::Relaxed);
+    }
+}
+
+impl AudioSink for AudioDriver {
+    fn play(&mut self) {
+        AudioDriver::play(self);
     }
 
-    // square wave goes brrrrrr
-    fn generate_square_wave(freq: f32, duration_secs: u64, sample_rate: u32) -> Vec<f32> {
-        (0..sample_rate as u64 * duration_secs)
-            .map(move |x| {
-                let t = x as f32 / sample_rate as f32;
-                if (t * freq * 2.0 * std::f32::consts::PI).sin() >= 0.0 {
-                    0.8 // High part of the wave
-                } else {
-                    -0.8 // Low part of the wave
-                }
-            })
-            .collect()
+    fn stop(&mut self) {
+        AudioDriver::stop(self);
     }
 }
@@ -0,0 +1,149 @@
+use clap::ValueEnum;
+use rodio::Source;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+// how long the tone takes to fade in and out, long enough to stop the clicks
+const ENVELOPE_SECS: f32 = 0.005;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Waveform {
+    Square,
+    Sine,
+    Triangle,
+    Noise,
+}
+
+// an endless tone that fades in and out depending on a shared flag.
+// the phase keeps running while it's silent so turning it back on doesn't pop
+pub struct Beeper {
+    tone_on: Arc<AtomicBool>,
+    frequency: f32,
+    volume: f32,
+    waveform: Waveform,
+    sample_rate: u32,
+    phase: f32,
+    envelope: f32,
+    envelope_step: f32,
+    noise_state: u32,
+    noise_value: f32,
+}
+
+impl Beeper {
+    // volume goes from 0.0 to 1.0
+    pub fn new(
+        tone_on: Arc<AtomicBool>,
+        frequency: f32,
+        volume: f32,
+        waveform: Waveform,
+        sample_rate: u32,
+    ) -> Beeper {
+        Beeper {
+            tone_on,
+            frequency,
+            volume: volume.clamp(0.0, 1.0),
+            waveform,
+            sample_rate,
+            phase: 0.0,
+            envelope: 0.0,
+            envelope_step: 1.0 / (ENVELOPE_SECS * sample_rate as f32),
+            noise_state: 0x1234_5678,
+            noise_value: 1.0,
+        }
+    }
+
+    // xorshift, plenty random for a buzz
+    fn next_noise(&mut self) {
+        self.noise_state ^= self.noise_state << 13;
+        self.noise_state ^= self.noise_state >> 17;
+        self.noise_state ^= self.noise_state << 5;
+        self.noise_value = (self.noise_state as f32 / u32::MAX as f32) * 2.0 - 1.0;
+    }
+}
+
+impl Iterator for Beeper {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let target = if self.tone_on.load(Ordering::Relaxed) {
+            1.0
+        } else {
+            0.0
+        };
+        if self.envelope < target {
+            self.envelope = (self.envelope + self.envelope_step).min(target);
+        } else if self.envelope > target {
+            self.envelope = (self.envelope - self.envelope_step).max(target);
+        }
+
+        let sample = match self.waveform {
+            Waveform::Square => {
+                if self.phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Waveform::Sine => (self.phase * std::f32::consts::TAU).sin(),
+            Waveform::Triangle => 1.0 - 4.0 * (self.phase - 0.5).abs(),
+            Waveform::Noise => self.noise_value,
+        };
+
+        let previous_phase = self.phase;
+        self.phase = (self.phase + self.frequency / self.sample_rate as f32).fract();
+        // the noise changes twice a period so the frequency still sets its pitch
+        let crossed_half = previous_phase < 0.5 && self.phase >= 0.5;
+        if self.waveform == Waveform::Noise && (self.phase < previous_phase || crossed_half) {
+            self.next_noise();
+        }
+
+        Some(sample * self.volume * self.envelope)
+    }
+}
+
+impl Source for Beeper {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn beeper_fades_in_and_out_test() {
+        let tone_on = Arc::new(AtomicBool::new(false));
+        let mut beeper = Beeper::new(tone_on.clone(), 441.0, 0.5, Waveform::Square, 44100);
+
+        assert!(beeper.by_ref().take(100).all(|sample| sample == 0.0));
+
+        tone_on.store(true, Ordering::Relaxed);
+        let attack: Vec<f32> = beeper.by_ref().take(441).collect();
+        // ramps up over 5ms instead of jumping straight to full volume
+        assert!(attack[0].abs() < 0.01);
+        assert_eq!(0.5, attack[440].abs());
+        assert!(attack.iter().all(|sample| sample.abs() <= 0.5));
+
+        tone_on.store(false, Ordering::Relaxed);
+        let release: Vec<f32> = beeper.by_ref().take(441).collect();
+        assert!(release[0].abs() > 0.49);
+        assert_eq!(0.0, release[440]);
+    }
+}
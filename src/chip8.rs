@@ -1,13 +1,44 @@
 extern crate rand;
-use crate::keyboard::Keyboard;
+use crate::keyboard::{KeyEvent, Keyboard};
 use crate::pixel::Pixel;
+use crate::quirks::Quirks;
+use crate::rom::read_rom;
 use rand::prelude::Rng;
-use sdl2::EventPump;
-use std::fs;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
 
 const DEFAULT_CHIP8_PIXEL_HEIGHT: u32 = 32;
 const DEFAULT_CHIP8_PIXEL_WIDTH: u32 = 64;
 
+// the built-in 4x5 hex digits, one byte per row with the pixels in the high nibble
+pub const FONT: [u8; 80] = [
+    0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
+    0x20, 0x60, 0x20, 0x20, 0x70, // 1
+    0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
+    0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
+    0x90, 0x90, 0xF0, 0x10, 0x10, // 4
+    0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
+    0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
+    0xF0, 0x10, 0x20, 0x40, 0x40, // 7
+    0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
+    0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
+    0xF0, 0x90, 0xF0, 0x90, 0x90, // A
+    0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
+    0xF0, 0x80, 0x80, 0x80, 0xF0, // C
+    0xE0, 0x90, 0x90, 0x90, 0xE0, // D
+    0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
+    0xF0, 0x80, 0xF0, 0x80, 0x80, // F
+];
+
+// FX0A stops the cpu until a key goes down and comes back up, like the VIP.
+// a key that's already held when it starts has to be let go and pressed again
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeyWait {
+    Press { register: u8 },
+    Release { register: u8, key: u8 },
+}
+
 pub struct Chip8 {
     memory: [u8; 4096],
     registers: [u8; 16],
@@ -18,9 +49,12 @@ pub struct Chip8 {
     sound_timer: u8,
     pub vram: Vec<Vec<Pixel>>,
     pub vram_changed: bool,
-    pub vram_scale: usize,
-    awaiting_keypress: bool,
-    awaiting_keylift: bool,
+    key_wait: Option<KeyWait>,
+    // addresses stored to since the last take_written, only kept while the
+    // debugger wants them
+    written: Option<Vec<u16>>,
+    quirks: Quirks,
+    rng: StdRng,
 }
 
 impl Chip8 {
@@ -33,11 +67,12 @@ impl Chip8 {
             program_counter: 0x200, // programs start at 0x200
             delay_timer: 0,
             sound_timer: 0,
-            vram: Vec::new(),
+            vram: Chip8::blank_vram(),
             vram_changed: false,
-            vram_scale: 1,
-            awaiting_keypress: false,
-            awaiting_keylift: false,
+            key_wait: None,
+            written: None,
+            quirks: Quirks::default(),
+            rng: StdRng::from_entropy(),
         };
 
         chip8.load_sprites_into_memory();
@@ -45,35 +80,22 @@ impl Chip8 {
     }
 
     pub fn load_sprites_into_memory(&mut self) {
-        let built_in_sprites: [u8; 80] = [
-            0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
-            0x20, 0x60, 0x20, 0x20, 0x70, // 1
-            0xF0, 0x10, 0xF0, 0x80, 0xF0, // 2
-            0xF0, 0x10, 0xF0, 0x10, 0xF0, // 3
-            0x90, 0x90, 0xF0, 0x10, 0x10, // 4
-            0xF0, 0x80, 0xF0, 0x10, 0xF0, // 5
-            0xF0, 0x80, 0xF0, 0x90, 0xF0, // 6
-            0xF0, 0x10, 0x20, 0x40, 0x40, // 7
-            0xF0, 0x90, 0xF0, 0x90, 0xF0, // 8
-            0xF0, 0x90, 0xF0, 0x10, 0xF0, // 9
-            0xF0, 0x90, 0xF0, 0x90, 0x90, // A
-            0xE0, 0x90, 0xE0, 0x90, 0xE0, // B
-            0xF0, 0x80, 0x80, 0x80, 0xF0, // C
-            0xE0, 0x90, 0x90, 0x90, 0xE0, // D
-            0xF0, 0x80, 0xF0, 0x80, 0xF0, // E
-            0xF0, 0x80, 0xF0, 0x80, 0x80, // F
-        ];
-
-        built_in_sprites
-            .iter()
+        FONT.iter()
             .enumerate()
             .for_each(|(index, byte)| self.memory[index] = *byte);
     }
 
     pub fn load_rom(&mut self, rom_file_path: String) -> Result<(), String> {
-        let rom_contents = fs::read(rom_file_path).expect("Error opening file");
+        let rom_contents = read_rom(&rom_file_path)?.program;
 
         let mem_start_location = 0x200;
+        if rom_contents.len() > self.memory.len() - mem_start_location {
+            return Err(format!(
+                "ROM is too big to fit in memory. ROM size: {}, available: {}",
+                rom_contents.len(),
+                self.memory.len() - mem_start_location
+            ));
+        }
         for index in 0..rom_contents.len() {
             self.memory[mem_start_location + index] = rom_contents[index];
         }
@@ -81,38 +103,24 @@ impl Chip8 {
         Ok(())
     }
 
-    pub fn initialize_pixels(&mut self, height: u32, width: u32) -> Result<(), String> {
-        println!("H: {}", height);
-        println!("W: {}", width);
-        if height % DEFAULT_CHIP8_PIXEL_HEIGHT != 0 {
-            return Err(format!("Window height is not evenly divisible by default height. Window height: {}, default height: {}", height, DEFAULT_CHIP8_PIXEL_HEIGHT));
-        }
-
-        if width % DEFAULT_CHIP8_PIXEL_WIDTH != 0 {
-            return Err(format!("Window width is not evenly divisible by default width. Window width: {}, default width: {}", width, DEFAULT_CHIP8_PIXEL_WIDTH));
-        }
-
-        let height_scale = height / DEFAULT_CHIP8_PIXEL_HEIGHT;
-        let width_scale = width / DEFAULT_CHIP8_PIXEL_WIDTH;
-
-        if height_scale != width_scale {
-            return Err(format!("Window width scale and window height scale do not match. Width scale: {}, height scale: {}", width_scale, height_scale));
-        }
-
-        //arbitrarily chose height scale, it and width_scale should be equal here
-        // if theyre not, we're screwed!
-        self.vram_scale = height_scale as usize;
+    // 64 x 32 pixels, how big they end up on screen is up to the renderer
+    fn blank_vram() -> Vec<Vec<Pixel>> {
+        (0..DEFAULT_CHIP8_PIXEL_HEIGHT)
+            .map(|y_location| {
+                (0..DEFAULT_CHIP8_PIXEL_WIDTH)
+                    .map(|x_location| Pixel::new(x_location, y_location, false))
+                    .collect()
+            })
+            .collect()
+    }
 
-        // 64 x 32 pixels
-        for y_location in 0..DEFAULT_CHIP8_PIXEL_HEIGHT {
-            let mut row: Vec<Pixel> = Vec::new();
-            for x_location in 0..DEFAULT_CHIP8_PIXEL_WIDTH {
-                row.push(Pixel::new(x_location, y_location, false));
-            }
-            self.vram.push(row);
-        }
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
 
-        Ok(())
+    // makes CXNN repeatable, handy for tests and recordings
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
     }
 
     pub fn set_register_value(&mut self, register: u8, value: u8) {
@@ -129,6 +137,52 @@ impl Chip8 {
         self.sound_timer
     }
 
+    pub fn get_delay_timer(&self) -> u8 {
+        self.delay_timer
+    }
+
+    pub fn get_stack(&self) -> &[u16] {
+        &self.stack
+    }
+
+    pub fn get_registers(&self) -> &[u8; 16] {
+        &self.registers
+    }
+
+    pub fn get_program_counter(&self) -> u16 {
+        self.program_counter
+    }
+
+    pub fn get_index_register(&self) -> u16 {
+        self.index_register
+    }
+
+    pub fn get_memory(&self) -> &[u8; 4096] {
+        &self.memory
+    }
+
+    pub fn is_waiting_for_key(&self) -> bool {
+        self.key_wait.is_some()
+    }
+
+    pub fn set_write_tracking(&mut self, tracking: bool) {
+        self.written = tracking.then(Vec::new);
+    }
+
+    pub fn take_written(&mut self) -> Vec<u16> {
+        self.written
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    fn write_memory(&mut self, address: usize, value: u8) {
+        self.memory[address] = value;
+        if let Some(written) = &mut self.written {
+            written.push(address as u16);
+        }
+    }
+
     pub fn play_sound(&self) {
         //play sound
         //println!("Playing sound");
@@ -147,7 +201,31 @@ impl Chip8 {
         (left_byte << 8) | right_byte
     }
 
-    pub fn handle_next_instruction(&mut self, keyboard: &mut Keyboard, event_pump: &mut EventPump) {
+    // called once a frame with the keypad changes since the last one, before
+    // any instructions run
+    pub fn handle_key_events(&mut self, events: &[KeyEvent]) {
+        for event in events {
+            self.key_wait = match (self.key_wait, *event) {
+                (Some(KeyWait::Press { register }), KeyEvent::Pressed(key)) => {
+                    Some(KeyWait::Release { register, key })
+                }
+                (Some(KeyWait::Release { register, key }), KeyEvent::Released(released))
+                    if released == key =>
+                {
+                    self.registers[register as usize] = key;
+                    None
+                }
+                (key_wait, _) => key_wait,
+            };
+        }
+    }
+
+    pub fn handle_next_instruction(&mut self, keyboard: &Keyboard) {
+        // halted on FX0A
+        if self.key_wait.is_some() {
+            return;
+        }
+
         let instruction = Chip8::decode(
             self.memory[self.program_counter as usize],
             self.memory[self.program_counter as usize + 1],
@@ -222,14 +300,23 @@ impl Chip8 {
                 0x1 => {
                     // ("8XY1: sets Vx to Vx | Vy. Vx = Vx | Vy");
                     self.registers[x_index] = self.registers[x_index] | self.registers[y_index];
+                    if self.quirks.vf_reset {
+                        self.registers[0x0F] = 0;
+                    }
                 }
                 0x2 => {
                     // ("8XY2: sets Vx to Vx & Vy. Vx = Vx & Vy");
                     self.registers[x_index] = self.registers[x_index] & self.registers[y_index];
+                    if self.quirks.vf_reset {
+                        self.registers[0x0F] = 0;
+                    }
                 }
                 0x3 => {
                     // ("8XY3: sets Vx to Vx xor Vy. Vx = Vx ^ Vy");
                     self.registers[x_index] = self.registers[x_index] ^ self.registers[y_index];
+                    if self.quirks.vf_reset {
+                        self.registers[0x0F] = 0;
+                    }
                 }
                 0x4 => {
                     // ("8XY4: Adds Vy to Vx. VF(carry flag) is set to 1 when there's an overflow, and to 0 when there is not");
@@ -262,6 +349,9 @@ impl Chip8 {
                 0x6 => {
                     //                     ("8XY6: stores to least significant bit of Vx in VF and then shifts Vx to the right by 1. Vx = Vx >> 1");
 
+                    if !self.quirks.shift_vx {
+                        self.registers[x_index] = self.registers[y_index];
+                    }
                     let least_sig_bit = self.registers[x_index] & 0x01;
                     self.registers[x_index] = self.registers[x_index] >> 1;
                     self.registers[0x0F] = least_sig_bit;
@@ -282,6 +372,9 @@ impl Chip8 {
                 }
                 0xE => {
                     // ("8XYE: stores the most significant bit in VF and shifts VX to the left by 1. Vx = Vx << 1");
+                    if !self.quirks.shift_vx {
+                        self.registers[x_index] = self.registers[y_index];
+                    }
                     let most_sig_bit = (self.registers[x_index] & 0b1000_0000) >> 7;
                     self.registers[x_index] = self.registers[x_index] << 1;
                     self.registers[0x0F] = most_sig_bit;
@@ -301,19 +394,23 @@ impl Chip8 {
             }
             0xB => {
                 // ("BNNN: jumps to the address NNN plus V0. PC(program counter) = V0 + NNN");
-                self.program_counter = self.registers[0x00] as u16 + nnn;
+                // with the jump quirk this is BXNN: jump to XNN + Vx
+                let offset_register = if self.quirks.jump_vx { x_index } else { 0x00 };
+                self.program_counter = self.registers[offset_register] as u16 + nnn;
+                increment_program_counter = false;
             }
             0xC => {
                 // ("CXNN: sets Vx to the result of a bitwise and operation on a random number (typically 0 to 255) and NN. Vx = rand() & NN");
-                let random_number: u8 = rand::thread_rng().gen();
+                let random_number: u8 = self.rng.gen();
                 self.registers[x_index] = random_number & nn;
             }
             0xD => {
                 // ("DXYN: Draws a sprite at coordinate (VX, VY) that has a width of 8 pixels and a height of N pixels. Each row of 8 pixels is read as bit-coded starting from memory location I; I value does not change after the execution of this instruction. As described above, VF is set to 1 if any screen pixels are flipped from set to unset when the sprite is drawn, and to 0 if that does not happen");
                 //let ip = self.program_counter as usize;
                 self.registers[0x0F] = 0;
-                let x_location = self.registers[x_index];
-                let y_location = self.registers[y_index];
+                // the starting position always wraps, the sprite itself only wraps without the clipping quirk
+                let x_location = self.registers[x_index] as u32 % DEFAULT_CHIP8_PIXEL_WIDTH;
+                let y_location = self.registers[y_index] as u32 % DEFAULT_CHIP8_PIXEL_HEIGHT;
                 let index = self.index_register as usize;
 
                 for row_offset in 0..n as u32 {
@@ -323,12 +420,17 @@ impl Chip8 {
                         let and_val = 0b1000_0000 >> column_offset;
                         let pixel_val = ((row_byte & and_val) >> bit_shift_amount) == 1;
 
-                        let y_wrapped = ((y_location as u32 + row_offset)
-                            % DEFAULT_CHIP8_PIXEL_HEIGHT)
-                            as usize;
-                        let x_wrapped = ((x_location as u32 + column_offset)
-                            % DEFAULT_CHIP8_PIXEL_WIDTH)
-                            as usize;
+                        let y_unwrapped = y_location + row_offset;
+                        let x_unwrapped = x_location + column_offset;
+                        if self.quirks.clipping
+                            && (y_unwrapped >= DEFAULT_CHIP8_PIXEL_HEIGHT
+                                || x_unwrapped >= DEFAULT_CHIP8_PIXEL_WIDTH)
+                        {
+                            continue;
+                        }
+
+                        let y_wrapped = (y_unwrapped % DEFAULT_CHIP8_PIXEL_HEIGHT) as usize;
+                        let x_wrapped = (x_unwrapped % DEFAULT_CHIP8_PIXEL_WIDTH) as usize;
                         if pixel_val && self.vram[y_wrapped][x_wrapped].on {
                             self.registers[0x0F] = 1;
                             self.vram[y_wrapped][x_wrapped].turn_off();
@@ -365,28 +467,11 @@ impl Chip8 {
                     self.registers[x_index] = self.delay_timer;
                 }
                 0x000A => {
-                    // ("FX0A: A key press is awaited, and then stored in Vx (blocking operation, all instruction halted until next key event. probably a loop?)");
-                    self.awaiting_keypress = true;
-                    if self.awaiting_keypress {
-                        if let Some(key) = keyboard.get_state().iter().position(|&state| state) {
-                            keyboard.set_halt_key(key as u8);
-                            self.awaiting_keylift = true;
-                            self.awaiting_keypress = false;
-                        }
-                    }
-
-                    if self.awaiting_keylift {
-                        if !keyboard.get_state()[keyboard.get_halt_key()] {
-                            self.awaiting_keylift = false;
-                            self.registers[x_index] = keyboard.get_halt_key() as u8;
-                        }
-                    }
-                    self.awaiting_keypress =
-                        self.registers[x_index] != keyboard.get_halt_key() as u8;
-
-                    if self.awaiting_keypress || self.awaiting_keylift {
-                        self.program_counter = self.program_counter - 2;
-                    }
+                    // ("FX0A: A key press is awaited, and then stored in Vx (blocking operation, all instruction halted until next key event)");
+                    // the key events come in through handle_key_events
+                    self.key_wait = Some(KeyWait::Press {
+                        register: x_index as u8,
+                    });
                 }
                 0x0015 => {
                     // ("FX15: sets the delay timer to Vx. delay_timer(Vx)");
@@ -413,20 +498,24 @@ impl Chip8 {
                     let ones = register_x_val % 10;
                     let index = self.index_register as usize;
 
-                    self.memory[index] = hundreds;
-                    self.memory[index + 1] = tens;
-                    self.memory[index + 2] = ones;
+                    self.write_memory(index, hundreds);
+                    self.write_memory(index + 1, tens);
+                    self.write_memory(index + 2, ones);
                 }
                 0x0055 => {
                     // ("FX55: stores from V0 to Vx (including Vx) in memory, starting at address I. the offset from I is increased by 1 for each value written, but I itself is left unmodified. reg_dum(Vx, &I)");
                     let i = self.index_register as usize;
-                    self.registers
+                    let registers = self.registers;
+                    registers
                         .iter()
                         .take(x_index + 1) //+1 bc zero index
                         .enumerate()
                         .for_each(|(index, register)| {
-                            self.memory[i + index] = *register;
+                            self.write_memory(i + index, *register);
                         });
+                    if self.quirks.memory_increment {
+                        self.index_register = self.index_register + x_index as u16 + 1;
+                    }
                 }
                 0x0065 => {
                     // ("FX65: Fills from V0 to Vx (including Vx) with values from memory, starting at address I. the offset from I is increased by 1 for each value read, but I remains umodified.");
@@ -436,6 +525,9 @@ impl Chip8 {
                     mem_slice.iter().enumerate().for_each(|(index, mem_val)| {
                         self.registers[index] = *mem_val;
                     });
+                    if self.quirks.memory_increment {
+                        self.index_register = self.index_register + x_index as u16 + 1;
+                    }
                 }
                 _ => {}
             },
@@ -447,3 +539,57 @@ impl Chip8 {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn fx0a_waits_for_press_then_release_test() {
+        // F30A then 1202 (jump to itself)
+        let rom_path = std::env::temp_dir().join("chip8_fx0a_waits_test.ch8");
+        std::fs::write(&rom_path, [0xF3, 0x0A, 0x12, 0x02]).unwrap();
+        let mut chip8 = Chip8::new();
+        chip8
+            .load_rom(rom_path.to_string_lossy().into_owned())
+            .unwrap();
+        let keyboard = Keyboard::new();
+
+        // v3 already holding the key that gets pressed doesn't end the wait early
+        chip8.registers[3] = 7;
+        chip8.handle_next_instruction(&keyboard);
+        // 5 was held before FX0A ran, letting go of it isn't a key press
+        chip8.handle_key_events(&[KeyEvent::Released(5)]);
+        chip8.handle_key_events(&[KeyEvent::Pressed(7)]);
+        chip8.handle_next_instruction(&keyboard);
+        assert_eq!(
+            Some(KeyWait::Release {
+                register: 3,
+                key: 7
+            }),
+            chip8.key_wait
+        );
+        assert_eq!(0x202, chip8.program_counter);
+
+        chip8.handle_key_events(&[KeyEvent::Pressed(2), KeyEvent::Released(7)]);
+        assert_eq!(None, chip8.key_wait);
+        assert_eq!(7, chip8.registers[3]);
+    }
+
+    #[test]
+    fn bnnn_jumps_to_v0_plus_nnn_test() {
+        // 6002 (v0 = 2) then B300, which lands on 0302 and not the one after
+        let rom_path = std::env::temp_dir().join("chip8_bnnn_jumps_test.ch8");
+        std::fs::write(&rom_path, [0x60, 0x02, 0xB3, 0x00]).unwrap();
+        let mut chip8 = Chip8::new();
+        chip8
+            .load_rom(rom_path.to_string_lossy().into_owned())
+            .unwrap();
+        let keyboard = Keyboard::new();
+
+        chip8.handle_next_instruction(&keyboard);
+        chip8.handle_next_instruction(&keyboard);
+        assert_eq!(0x302, chip8.program_counter);
+    }
+}
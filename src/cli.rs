@@ -0,0 +1,306 @@
+use crate::beeper::Waveform;
+use crate::filters::Filter;
+use crate::frontend::Frontend;
+use crate::palette::{Theme, BACKGROUND, FOREGROUND};
+use crate::phosphor::Persistence;
+use crate::quirks::QuirkPreset;
+use crate::screenshot::ScreenshotFormat;
+use crate::settings::{Rgb, Settings};
+use crate::video_recorder::VideoFormat;
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "chip8-emulator",
+    version,
+    about = "A CHIP-8 emulator, assembler and disassembler"
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Run a ROM
+    Run(Box<RunArgs>),
+    /// Assemble a source file into a ROM
+    Asm {
+        /// Source file to assemble
+        input: PathBuf,
+        /// Where to write the ROM
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Print the disassembly of a ROM
+    Disasm {
+        /// ROM to disassemble
+        rom: PathBuf,
+    },
+}
+
+// everything is optional so that only the flags that were actually passed
+// override the settings underneath
+#[derive(Args, Debug)]
+pub struct RunArgs {
+    /// Path to the ROM to run
+    pub rom: String,
+
+    /// Config file to use instead of the one in the user config directory
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Print the effective settings as TOML and exit
+    #[arg(long)]
+    pub print_config: bool,
+
+    /// Show the emulator in a window or in the terminal
+    #[arg(long, value_enum)]
+    pub frontend: Option<Frontend>,
+
+    /// Starting window size as a multiple of 64x32
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..=64))]
+    pub scale: Option<u32>,
+
+    /// Only scale the picture by whole numbers, leaving a border if needed
+    #[arg(long)]
+    pub integer_scaling: bool,
+
+    /// Start in fullscreen, F11 toggles it while running
+    #[arg(long)]
+    pub fullscreen: bool,
+
+    /// Show a clickable keypad in the window, F7 toggles it while running
+    #[arg(long)]
+    pub onscreen_keypad: bool,
+
+    /// Show fps, speed and registers over the game, F1 toggles it while running
+    #[arg(long)]
+    pub hud: bool,
+
+    /// Open the debugger window and start paused, F4 toggles it while running
+    #[arg(long)]
+    pub debugger: bool,
+
+    /// Instructions executed per 60Hz frame
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..=100_000))]
+    pub speed: Option<u32>,
+
+    /// How many times faster the game runs while fast-forwarding (Tab)
+    #[arg(long, value_name = "TIMES", value_parser = clap::value_parser!(u32).range(2..=16))]
+    pub fast_forward: Option<u32>,
+
+    /// How many times slower the game runs in slow motion (`)
+    #[arg(long, value_name = "TIMES", value_parser = clap::value_parser!(u32).range(2..=16))]
+    pub slow_motion: Option<u32>,
+
+    /// Which interpreter's behaviour to emulate
+    #[arg(long, value_enum)]
+    pub quirks: Option<QuirkPreset>,
+
+    /// Built-in colour scheme
+    #[arg(long, value_enum)]
+    pub theme: Option<Theme>,
+
+    /// Colour of lit pixels, e.g. #FFCC00
+    #[arg(long, value_name = "COLOR", value_parser = parse_color)]
+    pub foreground: Option<Rgb>,
+
+    /// Colour of unlit pixels, e.g. #996600
+    #[arg(long, value_name = "COLOR", value_parser = parse_color)]
+    pub background: Option<Rgb>,
+
+    /// Keep pixels glowing after they switch off to hide sprite flicker
+    #[arg(long, value_enum)]
+    pub persistence: Option<Persistence>,
+
+    /// Percent of brightness a pixel loses each frame with --persistence phosphor
+    #[arg(long, value_name = "PERCENT", value_parser = clap::value_parser!(u32).range(1..=100))]
+    pub phosphor_decay: Option<u32>,
+
+    /// Post-processing applied to the picture, can be changed with F3 while running
+    #[arg(long, value_enum)]
+    pub filter: Option<Filter>,
+
+    /// Don't open an audio device
+    #[arg(long)]
+    pub no_audio: bool,
+
+    /// Pitch of the beeper in Hz
+    #[arg(long, value_name = "HZ", value_parser = clap::value_parser!(u32).range(20..=20_000))]
+    pub audio_frequency: Option<u32>,
+
+    /// Beeper volume in percent
+    #[arg(long, value_parser = clap::value_parser!(u32).range(0..=100))]
+    pub volume: Option<u32>,
+
+    /// Shape of the beeper tone
+    #[arg(long, value_enum)]
+    pub waveform: Option<Waveform>,
+
+    /// Seed for the random number generator used by CXNN
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Run without a window, audio or input
+    #[arg(long)]
+    pub headless: bool,
+
+    /// Also render the beeper into this WAV file, frame by frame
+    #[arg(long, value_name = "PATH")]
+    pub record_audio: Option<PathBuf>,
+
+    /// Where F12 saves screenshots
+    #[arg(long, value_name = "DIR")]
+    pub screenshot_dir: Option<PathBuf>,
+
+    /// What F12 saves the screen as
+    #[arg(long, value_enum)]
+    pub screenshot_format: Option<ScreenshotFormat>,
+
+    /// Scale PNG screenshots up from the native 64x32
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..=64))]
+    pub screenshot_scale: Option<u32>,
+
+    /// Record video from the first frame, - writes to stdout. F9 starts and stops recording too
+    #[arg(long, value_name = "PATH")]
+    pub record_video: Option<PathBuf>,
+
+    /// Video format, guessed from the file extension by default (y4m for stdout)
+    #[arg(long, value_enum)]
+    pub video_format: Option<VideoFormat>,
+
+    /// Scale recorded video up from the native 64x32
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..=16))]
+    pub video_scale: Option<u32>,
+
+    /// Save the keypad state of every frame to this file, for replaying later
+    #[arg(long, value_name = "PATH")]
+    pub record_input: Option<PathBuf>,
+
+    /// Replay keypad input saved with --record-input, quitting at the end of it
+    #[arg(long, value_name = "PATH")]
+    pub play_input: Option<PathBuf>,
+
+    /// Stop after this many frames
+    #[arg(long)]
+    pub frames: Option<u64>,
+}
+
+impl RunArgs {
+    pub fn apply(&self, settings: &mut Settings) {
+        if let Some(frontend) = self.frontend {
+            settings.frontend = frontend;
+        }
+        if let Some(scale) = self.scale {
+            settings.scale = scale;
+        }
+        if self.integer_scaling {
+            settings.integer_scaling = true;
+        }
+        if self.fullscreen {
+            settings.fullscreen = true;
+        }
+        if self.onscreen_keypad {
+            settings.onscreen_keypad = true;
+        }
+        if self.hud {
+            settings.hud = true;
+        }
+        if self.debugger {
+            settings.debugger = true;
+        }
+        if let Some(speed) = self.speed {
+            settings.instructions_per_frame = speed;
+        }
+        if let Some(fast_forward) = self.fast_forward {
+            settings.fast_forward = fast_forward;
+        }
+        if let Some(slow_motion) = self.slow_motion {
+            settings.slow_motion = slow_motion;
+        }
+        if let Some(quirks) = self.quirks {
+            settings.quirks = quirks;
+            settings.custom_quirks = None;
+        }
+        if let Some(theme) = self.theme {
+            settings.theme = theme;
+            settings.palette = theme.palette();
+        }
+        if let Some(foreground) = self.foreground {
+            settings.palette.colors[FOREGROUND] = foreground;
+        }
+        if let Some(background) = self.background {
+            settings.palette.colors[BACKGROUND] = background;
+        }
+        if let Some(persistence) = self.persistence {
+            settings.persistence = persistence;
+        }
+        if let Some(phosphor_decay) = self.phosphor_decay {
+            settings.phosphor_decay = phosphor_decay;
+        }
+        if let Some(filter) = self.filter {
+            settings.filter = filter;
+        }
+        if self.no_audio {
+            settings.audio = false;
+        }
+        if let Some(audio_frequency) = self.audio_frequency {
+            settings.audio_frequency = audio_frequency;
+        }
+        if let Some(volume) = self.volume {
+            settings.volume = volume;
+        }
+        if let Some(waveform) = self.waveform {
+            settings.waveform = waveform;
+        }
+        if self.seed.is_some() {
+            settings.seed = self.seed;
+        }
+        if self.headless {
+            settings.headless = true;
+        }
+        if self.record_audio.is_some() {
+            settings.record_audio = self.record_audio.clone();
+        }
+        if let Some(screenshot_dir) = &self.screenshot_dir {
+            settings.screenshot_dir = screenshot_dir.clone();
+        }
+        if let Some(screenshot_format) = self.screenshot_format {
+            settings.screenshot_format = screenshot_format;
+        }
+        if let Some(screenshot_scale) = self.screenshot_scale {
+            settings.screenshot_scale = screenshot_scale;
+        }
+        if self.record_video.is_some() {
+            settings.record_video = self.record_video.clone();
+        }
+        if self.video_format.is_some() {
+            settings.video_format = self.video_format;
+        }
+        if let Some(video_scale) = self.video_scale {
+            settings.video_scale = video_scale;
+        }
+        if self.record_input.is_some() {
+            settings.record_input = self.record_input.clone();
+        }
+        if self.play_input.is_some() {
+            settings.play_input = self.play_input.clone();
+        }
+        if self.frames.is_some() {
+            settings.frames = self.frames;
+        }
+    }
+}
+
+// accepts RRGGBB with or without a leading #
+pub fn parse_color(text: &str) -> Result<Rgb, String> {
+    let hex = text.strip_prefix('#').unwrap_or(text);
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(String::from("expected a colour like #RRGGBB"));
+    }
+
+    let channel = |start: usize| u8::from_str_radix(&hex[start..start + 2], 16).unwrap();
+    Ok((channel(0), channel(2), channel(4)))
+}
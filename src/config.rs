@@ -0,0 +1,669 @@
+use crate::beeper::Waveform;
+use crate::cli::{parse_color, RunArgs};
+use crate::database;
+use crate::filters::Filter;
+use crate::frontend::Frontend;
+use crate::keyboard::parse_keypad_key;
+use crate::macros::Macro;
+use crate::palette::{Theme, BACKGROUND, FOREGROUND};
+use crate::phosphor::Persistence;
+use crate::quirks::QuirkPreset;
+use crate::rom::read_rom;
+use crate::screenshot::ScreenshotFormat;
+use crate::settings::{Rgb, Settings};
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml_edit::DocumentMut;
+
+// config.toml looks like this, every key is optional:
+//
+//   [global]
+//   speed = 15
+//   theme = "amber"
+//   foreground = "#FFCC00"
+//
+//   [global.keys]
+//   5 = ["W", "Up"]
+//   0 = "Space"
+//
+//   [global.gamepad]
+//   a = "6"
+//
+//   [roms.<sha1 of the rom>]
+//   quirks = "schip"
+//
+// built-in defaults < [global] < rom database < octocart options < [roms.<sha1>]
+// < command line flags
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    pub global: ConfigSection,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub roms: HashMap<String, ConfigSection>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigSection {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frontend: Option<Frontend>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scale: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub integer_scaling: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fullscreen: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub onscreen_keypad: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hud: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub debugger: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speed: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fast_forward: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub slow_motion: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quirks: Option<QuirkPreset>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub theme: Option<Theme>,
+    // background, plane 1, plane 2, both planes. replaces the theme's colours
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub colors: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub foreground: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub persistence: Option<Persistence>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phosphor_decay: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<Filter>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audio: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audio_frequency: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub volume: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub waveform: Option<Waveform>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub screenshot_dir: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub screenshot_format: Option<ScreenshotFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub screenshot_scale: Option<u32>,
+    // keypad key (0-F) -> sdl scancode name(s), e.g. "W" or ["W", "Up"]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keys: Option<BTreeMap<String, HostKeys>>,
+    // gamepad control -> keypad key, e.g. dpup = "2", "leftx-" = "4", b = "none"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gamepad: Option<BTreeMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gamepad_deadzone: Option<u32>,
+    // host key or "pad:<control>" -> keypad key, e.g. Space = "5"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub turbo: Option<BTreeMap<String, String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub turbo_rate: Option<u32>,
+    // host key or "pad:<control>" -> sequence, e.g. M = "5:10 -:2 8"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub macros: Option<BTreeMap<String, String>>,
+}
+
+// one key or a list of them, an empty list leaves the keypad key unbound
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum HostKeys {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl HostKeys {
+    fn to_vec(&self) -> Vec<String> {
+        match self {
+            HostKeys::One(host_key) => vec![host_key.clone()],
+            HostKeys::Many(host_keys) => host_keys.clone(),
+        }
+    }
+}
+
+impl ConfigFile {
+    pub fn load(path: &Path) -> Result<ConfigFile, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Error opening {}: {}", path.display(), e))?;
+        ConfigFile::parse(&contents).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn parse(contents: &str) -> Result<ConfigFile, String> {
+        toml::from_str(contents).map_err(|e| e.to_string())
+    }
+}
+
+// gamepad and turbo bindings name a keypad key or "none", stored the way
+// the keyboard reads them back
+fn keypad_key_setting(keypad_key: &str) -> Result<String, String> {
+    Ok(match parse_keypad_key(keypad_key)? {
+        Some(key) => format!("{:X}", key),
+        None => String::from("none"),
+    })
+}
+
+impl ConfigSection {
+    pub fn apply(&self, settings: &mut Settings, section_name: &str) -> Result<(), String> {
+        let invalid = |key: &str, message: String| format!("{}.{}: {}", section_name, key, message);
+
+        if let Some(frontend) = self.frontend {
+            settings.frontend = frontend;
+        }
+        if let Some(scale) = self.scale {
+            if !(1..=64).contains(&scale) {
+                return Err(invalid("scale", format!("{} is not in 1..=64", scale)));
+            }
+            settings.scale = scale;
+        }
+        if let Some(integer_scaling) = self.integer_scaling {
+            settings.integer_scaling = integer_scaling;
+        }
+        if let Some(fullscreen) = self.fullscreen {
+            settings.fullscreen = fullscreen;
+        }
+        if let Some(onscreen_keypad) = self.onscreen_keypad {
+            settings.onscreen_keypad = onscreen_keypad;
+        }
+        if let Some(hud) = self.hud {
+            settings.hud = hud;
+        }
+        if let Some(debugger) = self.debugger {
+            settings.debugger = debugger;
+        }
+        if let Some(speed) = self.speed {
+            if !(1..=100_000).contains(&speed) {
+                return Err(invalid("speed", format!("{} is not in 1..=100000", speed)));
+            }
+            settings.instructions_per_frame = speed;
+        }
+        if let Some(fast_forward) = self.fast_forward {
+            if !(2..=16).contains(&fast_forward) {
+                return Err(invalid(
+                    "fast_forward",
+                    format!("{} is not in 2..=16", fast_forward),
+                ));
+            }
+            settings.fast_forward = fast_forward;
+        }
+        if let Some(slow_motion) = self.slow_motion {
+            if !(2..=16).contains(&slow_motion) {
+                return Err(invalid(
+                    "slow_motion",
+                    format!("{} is not in 2..=16", slow_motion),
+                ));
+            }
+            settings.slow_motion = slow_motion;
+        }
+        if let Some(quirks) = self.quirks {
+            settings.quirks = quirks;
+            settings.custom_quirks = None;
+        }
+        if let Some(theme) = self.theme {
+            settings.theme = theme;
+            settings.palette = theme.palette();
+        }
+        if let Some(colors) = &self.colors {
+            if colors.len() > 4 {
+                return Err(invalid(
+                    "colors",
+                    format!("expected at most 4 colours, got {}", colors.len()),
+                ));
+            }
+            for (index, color) in colors.iter().enumerate() {
+                settings.palette.colors[index] =
+                    parse_color(color).map_err(|e| invalid("colors", e))?;
+            }
+        }
+        if let Some(foreground) = &self.foreground {
+            settings.palette.colors[FOREGROUND] =
+                parse_color(foreground).map_err(|e| invalid("foreground", e))?;
+        }
+        if let Some(background) = &self.background {
+            settings.palette.colors[BACKGROUND] =
+                parse_color(background).map_err(|e| invalid("background", e))?;
+        }
+        if let Some(persistence) = self.persistence {
+            settings.persistence = persistence;
+        }
+        if let Some(phosphor_decay) = self.phosphor_decay {
+            if !(1..=100).contains(&phosphor_decay) {
+                return Err(invalid(
+                    "phosphor_decay",
+                    format!("{} is not in 1..=100", phosphor_decay),
+                ));
+            }
+            settings.phosphor_decay = phosphor_decay;
+        }
+        if let Some(filter) = self.filter {
+            settings.filter = filter;
+        }
+        if let Some(audio) = self.audio {
+            settings.audio = audio;
+        }
+        if let Some(audio_frequency) = self.audio_frequency {
+            if !(20..=20_000).contains(&audio_frequency) {
+                return Err(invalid(
+                    "audio_frequency",
+                    format!("{} is not in 20..=20000", audio_frequency),
+                ));
+            }
+            settings.audio_frequency = audio_frequency;
+        }
+        if let Some(volume) = self.volume {
+            if volume > 100 {
+                return Err(invalid("volume", format!("{} is not in 0..=100", volume)));
+            }
+            settings.volume = volume;
+        }
+        if let Some(waveform) = self.waveform {
+            settings.waveform = waveform;
+        }
+        if let Some(screenshot_dir) = &self.screenshot_dir {
+            settings.screenshot_dir = screenshot_dir.clone();
+        }
+        if let Some(screenshot_format) = self.screenshot_format {
+            settings.screenshot_format = screenshot_format;
+        }
+        if let Some(screenshot_scale) = self.screenshot_scale {
+            if !(1..=64).contains(&screenshot_scale) {
+                return Err(invalid(
+                    "screenshot_scale",
+                    format!("{} is not in 1..=64", screenshot_scale),
+                ));
+            }
+            settings.screenshot_scale = screenshot_scale;
+        }
+        if let Some(keys) = &self.keys {
+            for (keypad_key, host_keys) in keys {
+                let keypad_key = keypad_key.to_uppercase();
+                if !settings.keys.contains_key(&keypad_key) {
+                    return Err(invalid(
+                        "keys",
+                        format!("'{}' is not a keypad key (0-F)", keypad_key),
+                    ));
+                }
+                settings.keys.insert(keypad_key, host_keys.to_vec());
+            }
+        }
+        if let Some(gamepad) = &self.gamepad {
+            for (control, keypad_key) in gamepad {
+                let keypad_key =
+                    keypad_key_setting(keypad_key).map_err(|e| invalid("gamepad", e))?;
+                settings.gamepad.insert(control.to_lowercase(), keypad_key);
+            }
+        }
+        if let Some(gamepad_deadzone) = self.gamepad_deadzone {
+            if gamepad_deadzone > 95 {
+                return Err(invalid(
+                    "gamepad_deadzone",
+                    format!("{} is not in 0..=95", gamepad_deadzone),
+                ));
+            }
+            settings.gamepad_deadzone = gamepad_deadzone;
+        }
+        if let Some(turbo) = &self.turbo {
+            for (host_input, keypad_key) in turbo {
+                let keypad_key = keypad_key_setting(keypad_key).map_err(|e| invalid("turbo", e))?;
+                settings.turbo.insert(host_input.clone(), keypad_key);
+            }
+        }
+        if let Some(turbo_rate) = self.turbo_rate {
+            if !(1..=30).contains(&turbo_rate) {
+                return Err(invalid(
+                    "turbo_rate",
+                    format!("{} is not in 1..=30", turbo_rate),
+                ));
+            }
+            settings.turbo_rate = turbo_rate;
+        }
+        if let Some(macros) = &self.macros {
+            for (host_input, sequence) in macros {
+                Macro::parse(sequence).map_err(|e| invalid("macros", e))?;
+                settings.macros.insert(host_input.clone(), sequence.clone());
+            }
+        }
+
+        Ok(())
+    }
+
+    // the inverse of apply, used to print the effective config
+    pub fn from_settings(settings: &Settings) -> ConfigSection {
+        let hex = |(r, g, b): Rgb| format!("#{:02X}{:02X}{:02X}", r, g, b);
+
+        ConfigSection {
+            frontend: Some(settings.frontend),
+            scale: Some(settings.scale),
+            integer_scaling: Some(settings.integer_scaling),
+            fullscreen: Some(settings.fullscreen),
+            onscreen_keypad: Some(settings.onscreen_keypad),
+            hud: Some(settings.hud),
+            debugger: Some(settings.debugger),
+            speed: Some(settings.instructions_per_frame),
+            fast_forward: Some(settings.fast_forward),
+            slow_motion: Some(settings.slow_motion),
+            quirks: Some(settings.quirks),
+            theme: Some(settings.theme),
+            colors: Some(
+                settings
+                    .palette
+                    .colors
+                    .iter()
+                    .map(|color| hex(*color))
+                    .collect(),
+            ),
+            foreground: None,
+            background: None,
+            persistence: Some(settings.persistence),
+            phosphor_decay: Some(settings.phosphor_decay),
+            filter: Some(settings.filter),
+            audio: Some(settings.audio),
+            audio_frequency: Some(settings.audio_frequency),
+            volume: Some(settings.volume),
+            waveform: Some(settings.waveform),
+            screenshot_dir: Some(settings.screenshot_dir.clone()),
+            screenshot_format: Some(settings.screenshot_format),
+            screenshot_scale: Some(settings.screenshot_scale),
+            keys: Some(
+                settings
+                    .keys
+                    .iter()
+                    .map(|(keypad_key, host_keys)| {
+                        (keypad_key.clone(), HostKeys::Many(host_keys.clone()))
+                    })
+                    .collect(),
+            ),
+            gamepad: Some(settings.gamepad.clone()),
+            gamepad_deadzone: Some(settings.gamepad_deadzone),
+            turbo: Some(settings.turbo.clone()),
+            turbo_rate: Some(settings.turbo_rate),
+            macros: Some(settings.macros.clone()),
+        }
+    }
+}
+
+pub fn default_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("chip8-emulator").join("config.toml"))
+}
+
+pub fn sha1_hex(bytes: &[u8]) -> String {
+    Sha1::digest(bytes)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+// stacks up the defaults, the config file and the command line
+pub fn resolve_settings(args: &RunArgs) -> Result<Settings, String> {
+    let mut settings = Settings::default();
+
+    // an explicit --config has to exist, the default one is optional
+    let config_file = match (&args.config, default_config_path()) {
+        (Some(path), _) => Some(ConfigFile::load(path)?),
+        (None, Some(path)) if path.exists() => Some(ConfigFile::load(&path)?),
+        _ => None,
+    };
+
+    let rom = read_rom(&args.rom)?;
+    let rom_hash = sha1_hex(&rom.program);
+    settings.config_path = args.config.clone().or_else(default_config_path);
+    settings.rom_sha1 = Some(rom_hash.clone());
+    settings.rom_name = Path::new(&args.rom)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned());
+
+    if let Some(config_file) = &config_file {
+        config_file.global.apply(&mut settings, "global")?;
+    }
+
+    // the database knows what the rom needs, but the user still gets the last word
+    if let Some(rom_info) = database::lookup(&rom_hash) {
+        rom_info.apply(&mut settings);
+        settings.rom_info = Some(rom_info);
+    }
+
+    if let Some(octo_options) = &rom.octo_options {
+        octo_options
+            .apply(&mut settings)
+            .map_err(|e| format!("{}: octocart options: {}", args.rom, e))?;
+    }
+
+    if let Some(rom_section) = config_file.and_then(|file| file.roms.get(&rom_hash).cloned()) {
+        rom_section.apply(&mut settings, &format!("roms.{}", rom_hash))?;
+    }
+
+    args.apply(&mut settings);
+    Ok(settings)
+}
+
+// remembers a theme picked at runtime in the rom's own section. any colours
+// in that section are dropped, otherwise they would win over the theme next time
+pub fn save_rom_theme(path: &Path, rom_sha1: &str, theme: Theme) -> Result<(), String> {
+    edit_config(path, |document| {
+        let rom_section = section_mut(document, Some(rom_sha1))?;
+        for key in ["colors", "foreground", "background"] {
+            rom_section.remove(key);
+        }
+        rom_section["theme"] = toml_edit::value(theme.name());
+        Ok(())
+    })
+}
+
+// writes the whole binding table from the rebinding screen, into [global] or
+// the rom's own section
+pub fn save_bindings(
+    path: &Path,
+    rom_sha1: Option<&str>,
+    keys: &BTreeMap<String, Vec<String>>,
+    gamepad: &BTreeMap<String, String>,
+) -> Result<(), String> {
+    edit_config(path, |document| {
+        let section = section_mut(document, rom_sha1)?;
+
+        let mut keys_table = toml_edit::Table::new();
+        for (keypad_key, host_keys) in keys {
+            keys_table[keypad_key.as_str()] =
+                toml_edit::value(host_keys.iter().collect::<toml_edit::Array>());
+        }
+        section["keys"] = toml_edit::Item::Table(keys_table);
+
+        let mut gamepad_table = toml_edit::Table::new();
+        for (control, keypad_key) in gamepad {
+            gamepad_table[control.as_str()] = toml_edit::value(keypad_key);
+        }
+        section["gamepad"] = toml_edit::Item::Table(gamepad_table);
+        Ok(())
+    })
+}
+
+// changes made at runtime go through toml_edit so the user's comments and
+// formatting survive
+fn edit_config(
+    path: &Path,
+    edit: impl FnOnce(&mut DocumentMut) -> Result<(), String>,
+) -> Result<(), String> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(format!("Error opening {}: {}", path.display(), e)),
+    };
+    let mut document: DocumentMut = contents
+        .parse()
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    edit(&mut document).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Error creating {}: {}", dir.display(), e))?;
+    }
+    fs::write(path, document.to_string())
+        .map_err(|e| format!("Error writing {}: {}", path.display(), e))
+}
+
+// [global], or [roms.<sha1>] for a rom
+fn section_mut<'a>(
+    document: &'a mut DocumentMut,
+    rom_sha1: Option<&str>,
+) -> Result<&'a mut toml_edit::Table, String> {
+    let Some(rom_sha1) = rom_sha1 else {
+        return document
+            .entry("global")
+            .or_insert(toml_edit::table())
+            .as_table_mut()
+            .ok_or_else(|| String::from("global is not a table"));
+    };
+
+    let roms = document
+        .entry("roms")
+        .or_insert(toml_edit::table())
+        .as_table_mut()
+        .ok_or_else(|| String::from("roms is not a table"))?;
+    // no empty [roms] header above the rom sections
+    roms.set_implicit(true);
+    roms.entry(rom_sha1)
+        .or_insert(toml_edit::table())
+        .as_table_mut()
+        .ok_or_else(|| format!("roms.{} is not a table", rom_sha1))
+}
+
+pub fn print_config(args: &RunArgs, settings: &Settings) -> Result<String, String> {
+    let effective = ConfigFile {
+        global: ConfigSection::from_settings(settings),
+        roms: HashMap::new(),
+    };
+    let body = toml::to_string(&effective).map_err(|e| e.to_string())?;
+
+    let mut output = String::new();
+    if let Some(path) = &settings.config_path {
+        output += &format!("# config file: {}\n", path.display());
+    }
+    output += &format!("# rom: {}\n", args.rom);
+    if let Some(rom_sha1) = &settings.rom_sha1 {
+        output += &format!("# rom sha1: {}\n", rom_sha1);
+    }
+    if let Some(quirks) = settings.custom_quirks {
+        output += &format!("# custom quirks: {:?}\n", quirks);
+    }
+    if let Some(rom_info) = &settings.rom_info {
+        output += &format!("# rom database: {}\n", rom_info.display_name());
+    }
+    output += &body;
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn rom_section_overrides_global_test() {
+        let config_file = ConfigFile::parse(
+            r##"
+            [global]
+            speed = 20
+            foreground = "#FFCC00"
+
+            [global.keys]
+            5 = "Up"
+            6 = ["Right", "E"]
+
+            [roms.abc]
+            speed = 30
+            fast_forward = 8
+            quirks = "schip"
+
+            [roms.abc.gamepad]
+            dpup = "1"
+            a = "none"
+            "##,
+        )
+        .unwrap();
+
+        let mut settings = Settings::default();
+        config_file.global.apply(&mut settings, "global").unwrap();
+        config_file.roms["abc"]
+            .apply(&mut settings, "roms.abc")
+            .unwrap();
+
+        assert_eq!(30, settings.instructions_per_frame);
+        assert_eq!(8, settings.fast_forward);
+        assert_eq!(QuirkPreset::Schip, settings.quirks);
+        assert_eq!((0xFF, 0xCC, 0x00), settings.palette.colors[FOREGROUND]);
+        assert_eq!(vec!["Up"], settings.keys["5"]);
+        assert_eq!(vec!["Right", "E"], settings.keys["6"]);
+        assert_eq!(vec!["X"], settings.keys["0"]);
+        assert_eq!("1", settings.gamepad["dpup"]);
+        assert_eq!("none", settings.gamepad["a"]);
+        assert_eq!("4", settings.gamepad["dpleft"]);
+    }
+
+    #[test]
+    fn turbo_and_macro_config_errors_test() {
+        let config_file = ConfigFile::parse(
+            r##"
+            [global.turbo]
+            Z = "a"
+            X = "none"
+
+            [global.macros]
+            M = "5:10 -:2 8"
+            "##,
+        )
+        .unwrap();
+
+        let mut settings = Settings::default();
+        config_file.global.apply(&mut settings, "global").unwrap();
+
+        assert_eq!("A", settings.turbo["Z"]);
+        assert_eq!("none", settings.turbo["X"]);
+        assert_eq!("5:10 -:2 8", settings.macros["M"]);
+        let bad_macro = ConfigFile::parse("[global.macros]\nM = \"5:x\"\n").unwrap();
+        assert!(bad_macro.global.apply(&mut settings, "global").is_err());
+        let bad_turbo = ConfigFile::parse("[global.turbo]\nZ = \"G\"\n").unwrap();
+        assert!(bad_turbo.global.apply(&mut settings, "global").is_err());
+    }
+
+    #[test]
+    fn runtime_changes_keep_the_rest_test() {
+        let path = std::env::temp_dir().join("chip8_save_rom_theme_test.toml");
+        fs::write(
+            &path,
+            "# my settings\n[global]\nspeed = 20\n\n[roms.abc]\nforeground = \"#FFCC00\"\n",
+        )
+        .unwrap();
+
+        save_rom_theme(&path, "abc", Theme::Amber).unwrap();
+        save_rom_theme(&path, "def", Theme::Lcd).unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(contents.starts_with("# my settings\n"));
+        let config_file = ConfigFile::parse(&contents).unwrap();
+        assert_eq!(Some(20), config_file.global.speed);
+        assert_eq!(Some(Theme::Amber), config_file.roms["abc"].theme);
+        assert_eq!(None, config_file.roms["abc"].foreground);
+        assert_eq!(Some(Theme::Lcd), config_file.roms["def"].theme);
+
+        let mut settings = Settings::default();
+        settings
+            .keys
+            .insert(String::from("5"), vec![String::from("Up")]);
+        save_bindings(&path, None, &settings.keys, &settings.gamepad).unwrap();
+        let config_file = ConfigFile::load(&path).unwrap();
+        let mut reloaded = Settings::default();
+        config_file.global.apply(&mut reloaded, "global").unwrap();
+        assert_eq!(settings.keys, reloaded.keys);
+        assert_eq!(Some(Theme::Amber), config_file.roms["abc"].theme);
+    }
+}
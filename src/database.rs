@@ -0,0 +1,194 @@
+use crate::cli::parse_color;
+use crate::quirks::QuirkPreset;
+use crate::settings::{Rgb, Settings};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::OnceLock;
+
+// same layout as https://github.com/chip-8/chip-8-database, so the full
+// programs.json and sha1-hashes.json from there can be dropped in as is
+const PROGRAMS: &str = include_str!("database/programs.json");
+const SHA1_HASHES: &str = include_str!("database/sha1-hashes.json");
+
+#[derive(Deserialize)]
+struct Program {
+    title: String,
+    #[serde(default)]
+    authors: Vec<String>,
+    roms: HashMap<String, RomEntry>,
+}
+
+#[derive(Deserialize)]
+struct RomEntry {
+    #[serde(default)]
+    platforms: Vec<String>,
+    tickrate: Option<u32>,
+    #[serde(default)]
+    keys: BTreeMap<String, u8>,
+    colors: Option<Colors>,
+}
+
+#[derive(Deserialize)]
+struct Colors {
+    // background first, then the colour(s) for lit pixels
+    #[serde(default)]
+    pixels: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RomInfo {
+    pub title: String,
+    pub authors: Vec<String>,
+    pub platform: Option<QuirkPreset>,
+    pub tickrate: Option<u32>,
+    // what the game uses each keypad key for, e.g. "left" -> 4
+    pub keys: BTreeMap<String, u8>,
+    // background first, like the palette
+    pub colors: Vec<Rgb>,
+}
+
+struct Database {
+    // sha1 -> index into programs
+    hashes: HashMap<String, usize>,
+    programs: Vec<Program>,
+}
+
+// parsed the first time a rom is looked up
+fn database() -> &'static Database {
+    static DATABASE: OnceLock<Database> = OnceLock::new();
+    DATABASE.get_or_init(|| Database {
+        hashes: serde_json::from_str(SHA1_HASHES).expect("bundled sha1-hashes.json is broken"),
+        programs: serde_json::from_str(PROGRAMS).expect("bundled programs.json is broken"),
+    })
+}
+
+pub fn lookup(sha1: &str) -> Option<RomInfo> {
+    let database = database();
+    let program = database.programs.get(*database.hashes.get(sha1)?)?;
+    rom_info(program, sha1)
+}
+
+fn rom_info(program: &Program, sha1: &str) -> Option<RomInfo> {
+    let rom = program.roms.get(sha1)?;
+
+    // a colour we can't read is as good as no colours at all
+    let colors = rom
+        .colors
+        .as_ref()
+        .and_then(|colors| {
+            colors
+                .pixels
+                .iter()
+                .take(4)
+                .map(|color| parse_color(color).ok())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Some(RomInfo {
+        title: program.title.clone(),
+        authors: program.authors.clone(),
+        // first platform we know how to emulate wins
+        platform: rom.platforms.iter().find_map(|id| platform_preset(id)),
+        tickrate: rom.tickrate,
+        keys: rom.keys.clone(),
+        colors,
+    })
+}
+
+fn platform_preset(platform_id: &str) -> Option<QuirkPreset> {
+    match platform_id {
+        "originalChip8" | "hybridVIP" | "modernChip8" => Some(QuirkPreset::Chip8),
+        "chip48" | "superchip1" | "superchip" => Some(QuirkPreset::Schip),
+        "xochip" => Some(QuirkPreset::Xochip),
+        _ => None,
+    }
+}
+
+impl RomInfo {
+    pub fn apply(&self, settings: &mut Settings) {
+        if let Some(platform) = self.platform {
+            settings.quirks = platform;
+        }
+        if let Some(tickrate) = self.tickrate {
+            settings.instructions_per_frame = tickrate;
+        }
+        for (index, color) in self.colors.iter().enumerate() {
+            settings.palette.colors[index] = *color;
+        }
+        // the database says which keys the game moves with, point the gamepad at them
+        for (action, keypad_key) in &self.keys {
+            let controls: &[&str] = match action.as_str() {
+                "up" => &["dpup", "lefty-"],
+                "down" => &["dpdown", "lefty+"],
+                "left" => &["dpleft", "leftx-"],
+                "right" => &["dpright", "leftx+"],
+                "a" => &["a"],
+                "b" => &["b"],
+                _ => &[],
+            };
+            for control in controls {
+                settings
+                    .gamepad
+                    .insert(control.to_string(), format!("{:X}", keypad_key));
+            }
+        }
+    }
+
+    pub fn display_name(&self) -> String {
+        if self.authors.is_empty() {
+            self.title.clone()
+        } else {
+            format!("{} by {}", self.title, self.authors.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::config::sha1_hex;
+
+    #[test]
+    fn bundled_rom_lookup_test() {
+        let rom = std::fs::read("roms/particle_demo.ch8").unwrap();
+
+        let rom_info = lookup(&sha1_hex(&rom)).unwrap();
+
+        assert_eq!("Particle Demo by zeroZshadow", rom_info.display_name());
+        assert_eq!(Some(QuirkPreset::Chip8), rom_info.platform);
+        assert!(lookup("0000000000000000000000000000000000000000").is_none());
+    }
+
+    #[test]
+    fn rom_entry_tickrate_keys_and_colors_test() {
+        let program: Program = serde_json::from_str(
+            r##"{
+                "title": "Test game",
+                "roms": {
+                    "abc": {
+                        "platforms": ["superchip"],
+                        "tickrate": 30,
+                        "keys": {"left": 4, "right": 6, "a": 5},
+                        "colors": {"pixels": ["#000022", "#FFCC00"]}
+                    }
+                }
+            }"##,
+        )
+        .unwrap();
+
+        let info = rom_info(&program, "abc").unwrap();
+        let mut settings = Settings::default();
+        info.apply(&mut settings);
+
+        assert_eq!(QuirkPreset::Schip, settings.quirks);
+        assert_eq!(30, settings.instructions_per_frame);
+        assert_eq!((0x00, 0x00, 0x22), settings.palette.colors[0]);
+        assert_eq!((0xFF, 0xCC, 0x00), settings.palette.colors[1]);
+        assert_eq!("4", settings.gamepad["leftx-"]);
+        assert_eq!("6", settings.gamepad["dpright"]);
+        assert_eq!("5", settings.gamepad["a"]);
+        assert!(rom_info(&program, "def").is_none());
+    }
+}
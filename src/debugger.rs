@@ -0,0 +1,329 @@
+use crate::chip8::Chip8;
+use crate::disassembler::disassemble_instruction;
+use std::collections::{BTreeSet, HashMap};
+
+// the window is a grid of text cells. the buttons are along the top, the
+// disassembly and registers side by side under them and memory at the bottom
+const DISASSEMBLY_TOP: usize = 2;
+const DISASSEMBLY_ROWS: usize = 21;
+const REGISTERS_LEFT: usize = 34;
+const MEMORY_TOP: usize = DISASSEMBLY_TOP + DISASSEMBLY_ROWS + 1;
+pub const MEMORY_ROWS: usize = 16;
+const BYTES_PER_ROW: u16 = 16;
+const STACK_ROWS: usize = 8;
+pub const COLUMNS: usize = 56;
+pub const ROWS: usize = MEMORY_TOP + 1 + MEMORY_ROWS;
+// a second at 60 fps
+const WRITE_FADE_FRAMES: u32 = 60;
+
+// the buttons, by where they sit on the top row
+const PAUSE_BUTTON: (usize, usize) = (0, 10);
+const STEP_BUTTON: (usize, usize) = (11, 17);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextColor {
+    Normal,
+    Dim,
+    // the instruction about to run
+    Current,
+    Breakpoint,
+    // memory stored to in the last second
+    Written,
+    Button,
+}
+
+// a run of text at a cell in the window
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ViewText {
+    pub column: usize,
+    pub row: usize,
+    pub text: String,
+    pub color: TextColor,
+}
+
+// what a click on one of the buttons asks the emulator to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebuggerAction {
+    TogglePause,
+    Step,
+}
+
+// everything the debugger window remembers between frames. the window itself
+// only draws what view() hands it, so this part doesn't need sdl
+pub struct Debugger {
+    breakpoints: BTreeSet<u16>,
+    // address of the first row of the memory view
+    memory_top: u16,
+    // frames since each recently written address was stored to
+    writes: HashMap<u16, u32>,
+    // the breakpoint to run straight past after resuming or stepping off it
+    resume_from: Option<u16>,
+}
+
+impl Debugger {
+    pub fn new() -> Debugger {
+        Debugger {
+            breakpoints: BTreeSet::new(),
+            memory_top: 0x200,
+            writes: HashMap::new(),
+            resume_from: None,
+        }
+    }
+
+    pub fn toggle_breakpoint(&mut self, address: u16) {
+        if !self.breakpoints.remove(&address) {
+            self.breakpoints.insert(address);
+        }
+    }
+
+    // called before every instruction while the debugger is open
+    pub fn should_break(&mut self, program_counter: u16) -> bool {
+        if self.resume_from.take() == Some(program_counter) {
+            return false;
+        }
+        self.breakpoints.contains(&program_counter)
+    }
+
+    // the next instruction runs even if it's on a breakpoint
+    pub fn resume(&mut self, program_counter: u16) {
+        self.resume_from = Some(program_counter);
+    }
+
+    // scrolls the memory view by whole rows, negative is up
+    pub fn scroll(&mut self, rows: i32) {
+        let last_top = 4096 - MEMORY_ROWS as i32 * BYTES_PER_ROW as i32;
+        let top = self.memory_top as i32 + rows * BYTES_PER_ROW as i32;
+        self.memory_top = top.clamp(0, last_top) as u16;
+    }
+
+    // ages the highlights and adds this frame's writes
+    pub fn frame_done(&mut self, written: &[u16]) {
+        self.writes.retain(|_, age| {
+            *age += 1;
+            *age < WRITE_FADE_FRAMES
+        });
+        for address in written {
+            self.writes.insert(*address, 0);
+        }
+    }
+
+    // a click on a disassembly line toggles its breakpoint, a click on a
+    // button is handed back to the emulator
+    pub fn click(&mut self, column: usize, row: usize, chip8: &Chip8) -> Option<DebuggerAction> {
+        let within = |(left, right): (usize, usize)| (left..right).contains(&column);
+        if row == 0 && within(PAUSE_BUTTON) {
+            return Some(DebuggerAction::TogglePause);
+        }
+        if row == 0 && within(STEP_BUTTON) {
+            return Some(DebuggerAction::Step);
+        }
+        if column < REGISTERS_LEFT
+            && (DISASSEMBLY_TOP..DISASSEMBLY_TOP + DISASSEMBLY_ROWS).contains(&row)
+        {
+            let first = Debugger::first_disassembled(chip8.get_program_counter());
+            self.toggle_breakpoint(first + (row - DISASSEMBLY_TOP) as u16 * 2);
+        }
+        None
+    }
+
+    // keeps the current instruction in the middle of the listing where it can
+    fn first_disassembled(program_counter: u16) -> u16 {
+        let last_first = 4096 - DISASSEMBLY_ROWS as u16 * 2;
+        program_counter
+            .saturating_sub(DISASSEMBLY_ROWS as u16 / 2 * 2)
+            .min(last_first)
+    }
+
+    pub fn view(&self, chip8: &Chip8, paused: bool) -> Vec<ViewText> {
+        let mut view = Vec::new();
+        let mut text = |column: usize, row: usize, text: String, color: TextColor| {
+            view.push(ViewText {
+                column,
+                row,
+                text,
+                color,
+            })
+        };
+
+        let pause_label = if paused { "[CONTINUE]" } else { "[PAUSE]" };
+        text(PAUSE_BUTTON.0, 0, pause_label.into(), TextColor::Button);
+        text(STEP_BUTTON.0, 0, "[STEP]".into(), TextColor::Button);
+        let status = match (paused, chip8.is_waiting_for_key()) {
+            (true, _) => "PAUSED",
+            (false, true) => "WAITING FOR A KEY",
+            (false, false) => "RUNNING",
+        };
+        text(STEP_BUTTON.1 + 2, 0, status.into(), TextColor::Dim);
+
+        let memory = chip8.get_memory();
+        let program_counter = chip8.get_program_counter();
+        let first = Debugger::first_disassembled(program_counter);
+        for line in 0..DISASSEMBLY_ROWS {
+            let address = first + line as u16 * 2;
+            let instruction =
+                (memory[address as usize] as u16) << 8 | memory[address as usize + 1] as u16;
+            let current = if address == program_counter { '>' } else { ' ' };
+            let breakpoint = if self.breakpoints.contains(&address) {
+                '*'
+            } else {
+                ' '
+            };
+            let color = match (address == program_counter, breakpoint == '*') {
+                (true, _) => TextColor::Current,
+                (false, true) => TextColor::Breakpoint,
+                (false, false) => TextColor::Normal,
+            };
+            text(
+                0,
+                DISASSEMBLY_TOP + line,
+                format!(
+                    "{}{} {:04X}  {:04X}  {}",
+                    current,
+                    breakpoint,
+                    address,
+                    instruction,
+                    disassemble_instruction(instruction)
+                ),
+                color,
+            );
+        }
+
+        let registers = chip8.get_registers();
+        for row in 0..8 {
+            text(
+                REGISTERS_LEFT,
+                DISASSEMBLY_TOP + row,
+                format!(
+                    "V{:X} {:02X}  V{:X} {:02X}",
+                    row,
+                    registers[row],
+                    row + 8,
+                    registers[row + 8]
+                ),
+                TextColor::Normal,
+            );
+        }
+        text(
+            REGISTERS_LEFT,
+            DISASSEMBLY_TOP + 9,
+            format!(
+                "PC {:04X}  I {:04X}",
+                program_counter,
+                chip8.get_index_register()
+            ),
+            TextColor::Normal,
+        );
+        text(
+            REGISTERS_LEFT,
+            DISASSEMBLY_TOP + 10,
+            format!(
+                "DT {:02X}  ST {:02X}",
+                chip8.get_delay_timer(),
+                chip8.get_sound_timer()
+            ),
+            TextColor::Normal,
+        );
+
+        // newest call first, the oldest ones fall off the bottom
+        let stack = chip8.get_stack();
+        text(
+            REGISTERS_LEFT,
+            DISASSEMBLY_TOP + 12,
+            format!("STACK {}", stack.len()),
+            TextColor::Dim,
+        );
+        for (row, address) in stack.iter().rev().take(STACK_ROWS).enumerate() {
+            text(
+                REGISTERS_LEFT,
+                DISASSEMBLY_TOP + 13 + row,
+                format!("{:04X}", address),
+                TextColor::Normal,
+            );
+        }
+
+        text(
+            0,
+            MEMORY_TOP,
+            format!(
+                "MEMORY {:04X}-{:04X}",
+                self.memory_top,
+                self.memory_top + MEMORY_ROWS as u16 * BYTES_PER_ROW - 1
+            ),
+            TextColor::Dim,
+        );
+        for row in 0..MEMORY_ROWS {
+            let row_address = self.memory_top + row as u16 * BYTES_PER_ROW;
+            text(
+                0,
+                MEMORY_TOP + 1 + row,
+                format!("{:04X}", row_address),
+                TextColor::Dim,
+            );
+            for byte in 0..BYTES_PER_ROW {
+                let address = row_address + byte;
+                let color = if self.writes.contains_key(&address) {
+                    TextColor::Written
+                } else if address == program_counter || address == program_counter + 1 {
+                    TextColor::Current
+                } else {
+                    TextColor::Normal
+                };
+                text(
+                    6 + byte as usize * 3,
+                    MEMORY_TOP + 1 + row,
+                    format!("{:02X}", memory[address as usize]),
+                    color,
+                );
+            }
+        }
+        view
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn breakpoints_and_memory_view_test() {
+        let chip8 = Chip8::new();
+        let mut debugger = Debugger::new();
+
+        // pc 0200 is in the middle of the listing
+        let pc_row = DISASSEMBLY_TOP + DISASSEMBLY_ROWS / 2;
+        assert_eq!(None, debugger.click(3, pc_row, &chip8));
+        assert!(debugger.should_break(0x200));
+        debugger.resume(0x200);
+        assert!(!debugger.should_break(0x200));
+        assert!(debugger.should_break(0x200));
+
+        let view = debugger.view(&chip8, true);
+        let line = view.iter().find(|text| text.row == pc_row).unwrap();
+        assert!(line.text.starts_with(">* 0200  0000"));
+        assert_eq!(TextColor::Current, line.color);
+
+        debugger.frame_done(&[0x0205]);
+        let written = |debugger: &Debugger| {
+            debugger
+                .view(&chip8, true)
+                .into_iter()
+                .filter(|text| text.color == TextColor::Written)
+                .count()
+        };
+        assert_eq!(1, written(&debugger));
+        (0..WRITE_FADE_FRAMES).for_each(|_| debugger.frame_done(&[]));
+        assert_eq!(0, written(&debugger));
+
+        debugger.scroll(1000);
+        assert_eq!(0x0F00, debugger.memory_top);
+        debugger.scroll(-1000);
+        assert_eq!(0, debugger.memory_top);
+
+        assert_eq!(
+            Some(DebuggerAction::TogglePause),
+            debugger.click(1, 0, &chip8)
+        );
+        assert_eq!(Some(DebuggerAction::Step), debugger.click(12, 0, &chip8));
+    }
+}
@@ -0,0 +1,117 @@
+extern crate sdl2;
+use crate::debugger::{TextColor, ViewText, COLUMNS, ROWS};
+use crate::font;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::WindowCanvas;
+use sdl2::video::Window;
+use sdl2::VideoSubsystem;
+use std::cell::Cell;
+use std::rc::Rc;
+
+// font pixels are drawn this many window pixels wide
+const DOT: u32 = 2;
+pub const CELL_WIDTH: u32 = font::ADVANCE as u32 * DOT;
+pub const CELL_HEIGHT: u32 = (font::GLYPH_HEIGHT as u32 + 2) * DOT;
+const MARGIN: u32 = 8;
+
+// the input side needs to know which events belong to the debugger window,
+// None while it's closed
+pub type SharedWindowId = Rc<Cell<Option<u32>>>;
+
+pub struct DebuggerWindow {
+    canvas: WindowCanvas,
+    // what's on screen now, so an unchanged view isn't drawn again
+    shown: Vec<ViewText>,
+}
+
+impl DebuggerWindow {
+    // opens to the right of the game. drawn in software so it works without a
+    // gpu and with sdl's dummy video driver
+    pub fn build(
+        video_subsystem: &VideoSubsystem,
+        beside: &Window,
+    ) -> Result<DebuggerWindow, String> {
+        let (x, y) = beside.position();
+        let (width, _) = beside.size();
+        let window = video_subsystem
+            .window(
+                "CHIP-8 debugger",
+                COLUMNS as u32 * CELL_WIDTH + MARGIN * 2,
+                ROWS as u32 * CELL_HEIGHT + MARGIN * 2,
+            )
+            .position(x + width as i32 + MARGIN as i32, y)
+            .build()
+            .map_err(|e| e.to_string())?;
+        let canvas = window
+            .into_canvas()
+            .software()
+            .build()
+            .map_err(|e| e.to_string())?;
+        Ok(DebuggerWindow {
+            canvas,
+            shown: Vec::new(),
+        })
+    }
+
+    pub fn id(&self) -> u32 {
+        self.canvas.window().id()
+    }
+
+    // the text cell under a click, in window coordinates
+    pub fn cell_at(x: i32, y: i32) -> Option<(usize, usize)> {
+        let column = (x - MARGIN as i32).div_euclid(CELL_WIDTH as i32);
+        let row = (y - MARGIN as i32).div_euclid(CELL_HEIGHT as i32);
+        if column < 0 || row < 0 {
+            return None;
+        }
+        Some((column as usize, row as usize))
+    }
+
+    pub fn draw(&mut self, view: &[ViewText]) -> Result<(), String> {
+        if view == self.shown {
+            return Ok(());
+        }
+
+        self.canvas.set_draw_color(Color::RGB(24, 24, 32));
+        self.canvas.clear();
+        for color in [
+            TextColor::Normal,
+            TextColor::Dim,
+            TextColor::Current,
+            TextColor::Breakpoint,
+            TextColor::Written,
+            TextColor::Button,
+        ] {
+            let dots: Vec<Rect> = view
+                .iter()
+                .filter(|text| text.color == color)
+                .flat_map(|text| {
+                    let left = MARGIN + text.column as u32 * CELL_WIDTH;
+                    let top = MARGIN + text.row as u32 * CELL_HEIGHT;
+                    font::dots(&text.text).into_iter().map(move |(x, y)| {
+                        Rect::new(
+                            (left + x as u32 * DOT) as i32,
+                            (top + y as u32 * DOT) as i32,
+                            DOT,
+                            DOT,
+                        )
+                    })
+                })
+                .collect();
+            let (r, g, b) = match color {
+                TextColor::Normal => (220, 220, 220),
+                TextColor::Dim => (120, 120, 130),
+                TextColor::Current => (255, 210, 80),
+                TextColor::Breakpoint => (255, 90, 90),
+                TextColor::Written => (90, 200, 255),
+                TextColor::Button => (120, 230, 120),
+            };
+            self.canvas.set_draw_color(Color::RGB(r, g, b));
+            self.canvas.fill_rects(&dots)?;
+        }
+        self.canvas.present();
+        self.shown = view.to_vec();
+        Ok(())
+    }
+}
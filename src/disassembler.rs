@@ -0,0 +1,75 @@
+// turns opcodes back into the mnemonics from cowgod's chip-8 reference.
+// the output is valid input for the assembler, so `disasm` and `asm` round trip
+pub fn disassemble_instruction(instruction: u16) -> String {
+    let x = (instruction & 0x0F00) >> 8;
+    let y = (instruction & 0x00F0) >> 4;
+    let nnn = instruction & 0x0FFF;
+    let nn = instruction & 0x00FF;
+    let n = instruction & 0x000F;
+
+    match (instruction >> 12, x, y, n) {
+        (0x0, 0x0, 0xE, 0x0) => String::from("CLS"),
+        (0x0, 0x0, 0xE, 0xE) => String::from("RET"),
+        (0x0, ..) => format!("SYS #{:03X}", nnn),
+        (0x1, ..) => format!("JP #{:03X}", nnn),
+        (0x2, ..) => format!("CALL #{:03X}", nnn),
+        (0x3, ..) => format!("SE V{:X}, #{:02X}", x, nn),
+        (0x4, ..) => format!("SNE V{:X}, #{:02X}", x, nn),
+        (0x5, _, _, 0x0) => format!("SE V{:X}, V{:X}", x, y),
+        (0x6, ..) => format!("LD V{:X}, #{:02X}", x, nn),
+        (0x7, ..) => format!("ADD V{:X}, #{:02X}", x, nn),
+        (0x8, _, _, 0x0) => format!("LD V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x1) => format!("OR V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x2) => format!("AND V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x3) => format!("XOR V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x4) => format!("ADD V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x5) => format!("SUB V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x6) => format!("SHR V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x7) => format!("SUBN V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0xE) => format!("SHL V{:X}, V{:X}", x, y),
+        (0x9, _, _, 0x0) => format!("SNE V{:X}, V{:X}", x, y),
+        (0xA, ..) => format!("LD I, #{:03X}", nnn),
+        (0xB, ..) => format!("JP V0, #{:03X}", nnn),
+        (0xC, ..) => format!("RND V{:X}, #{:02X}", x, nn),
+        (0xD, ..) => format!("DRW V{:X}, V{:X}, {}", x, y, n),
+        (0xE, _, 0x9, 0xE) => format!("SKP V{:X}", x),
+        (0xE, _, 0xA, 0x1) => format!("SKNP V{:X}", x),
+        (0xF, _, 0x0, 0x7) => format!("LD V{:X}, DT", x),
+        (0xF, _, 0x0, 0xA) => format!("LD V{:X}, K", x),
+        (0xF, _, 0x1, 0x5) => format!("LD DT, V{:X}", x),
+        (0xF, _, 0x1, 0x8) => format!("LD ST, V{:X}", x),
+        (0xF, _, 0x1, 0xE) => format!("ADD I, V{:X}", x),
+        (0xF, _, 0x2, 0x9) => format!("LD F, V{:X}", x),
+        (0xF, _, 0x3, 0x3) => format!("LD B, V{:X}", x),
+        (0xF, _, 0x5, 0x5) => format!("LD [I], V{:X}", x),
+        (0xF, _, 0x6, 0x5) => format!("LD V{:X}, [I]", x),
+        _ => format!("DW #{:04X}", instruction),
+    }
+}
+
+// one line per two bytes, with the address and raw opcode tucked into a comment
+pub fn disassemble(rom: &[u8], start_address: u16) -> Vec<String> {
+    rom.chunks(2)
+        .enumerate()
+        .map(|(index, chunk)| {
+            let address = start_address as usize + index * 2;
+            match chunk {
+                [left_byte, right_byte] => {
+                    let instruction = ((*left_byte as u16) << 8) | *right_byte as u16;
+                    format!(
+                        "    {:<20} ; {:03X}: {:04X}",
+                        disassemble_instruction(instruction),
+                        address,
+                        instruction
+                    )
+                }
+                _ => format!(
+                    "    {:<20} ; {:03X}: {:02X}",
+                    format!("DB #{:02X}", chunk[0]),
+                    address,
+                    chunk[0]
+                ),
+            }
+        })
+        .collect()
+}
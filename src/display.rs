@@ -1,33 +1,143 @@
 extern crate sdl2;
+use crate::chip8::Chip8;
+use crate::debugger::ViewText;
+use crate::debugger_window::{DebuggerWindow, SharedWindowId};
+use crate::filters::Filter;
+use crate::frontend::VideoSink;
+use crate::keyboard::SharedKeypadArea;
+use crate::palette::Palette;
+use crate::phosphor::Phosphor;
+use crate::rebind::Rebinding;
 use crate::renderer::Renderer;
-use sdl2::EventPump;
+use crate::settings::Settings;
+use sdl2::{EventPump, GameControllerSubsystem, Sdl};
+use std::cell::Cell;
+use std::rc::Rc;
 
 pub struct DisplayDriver {
     pub renderer: Renderer,
-    pub event_pump: EventPump,
+    sdl_context: Sdl,
+    // the window title, put back when a prompt in it is done
+    title: String,
+    debugger_window: Option<DebuggerWindow>,
+    debugger_window_id: SharedWindowId,
 }
 
 impl DisplayDriver {
-    pub fn build() -> Result<DisplayDriver, String> {
+    pub fn build(settings: &Settings) -> Result<DisplayDriver, String> {
         let sdl_context = sdl2::init()?;
         let video_subsystem = sdl_context.video()?;
 
-        let window = video_subsystem
-            .window("rust-sdl2 demo: Video", 640, 320)
-            .position_centered()
-            .opengl()
-            .build()
-            .map_err(|e| e.to_string())?;
+        let title = match &settings.rom_info {
+            Some(rom_info) => format!("CHIP-8 - {}", rom_info.display_name()),
+            None => String::from("CHIP-8"),
+        };
+        // scale is only the starting size, the window can be resized freely after that
+        let mut window_builder =
+            video_subsystem.window(&title, 64 * settings.scale, 32 * settings.scale);
+        window_builder.position_centered().resizable();
+        if settings.fullscreen {
+            window_builder.fullscreen_desktop();
+        }
+        let window = window_builder.build().map_err(|e| e.to_string())?;
 
-        let event_pump = sdl_context.event_pump()?;
-        let renderer = Renderer::new(window)?;
+        let mut renderer = Renderer::new(
+            window,
+            settings.palette,
+            Phosphor::new(settings.persistence, settings.phosphor_decay),
+            settings.filter,
+            settings.integer_scaling,
+        )?;
+        renderer.set_onscreen_keypad(settings.onscreen_keypad);
         Ok(DisplayDriver {
-            renderer: renderer,
-            event_pump: event_pump,
+            renderer,
+            sdl_context,
+            title,
+            debugger_window: None,
+            debugger_window_id: Rc::new(Cell::new(None)),
         })
     }
 
-    pub fn get_window_dimensions(&self) -> (u32, u32) {
-        self.renderer.get_size().unwrap()
+    // sdl only hands out one event pump per context, so this can only be called once
+    pub fn event_pump(&self) -> Result<EventPump, String> {
+        self.sdl_context.event_pump()
+    }
+
+    pub fn keypad_area(&self) -> SharedKeypadArea {
+        self.renderer.keypad_area()
+    }
+
+    pub fn debugger_window_id(&self) -> SharedWindowId {
+        self.debugger_window_id.clone()
+    }
+
+    pub fn game_controller(&self) -> Result<GameControllerSubsystem, String> {
+        self.sdl_context.game_controller()
+    }
+}
+
+impl VideoSink for DisplayDriver {
+    fn draw(&mut self, chip8: &Chip8) -> Result<bool, String> {
+        self.renderer.draw(chip8)
+    }
+
+    fn set_palette(&mut self, palette: &Palette) {
+        self.renderer.set_palette(palette);
+    }
+
+    fn set_filter(&mut self, filter: Filter) {
+        self.renderer.set_filter(filter);
+    }
+
+    fn toggle_fullscreen(&mut self) -> Result<(), String> {
+        self.renderer.toggle_fullscreen()
+    }
+
+    fn show_keypad_state(&mut self, state: &[bool; 16]) {
+        self.renderer.set_keypad_state(state);
+    }
+
+    fn show_hud(&mut self, lines: Option<&[String]>) {
+        self.renderer.set_hud(lines);
+    }
+
+    fn toggle_keypad(&mut self) {
+        let shown = self.renderer.is_onscreen_keypad_shown();
+        self.renderer.set_onscreen_keypad(!shown);
+    }
+
+    fn show_debugger(&mut self, view: Option<&[ViewText]>) -> Result<(), String> {
+        let Some(view) = view else {
+            self.debugger_window = None;
+            self.debugger_window_id.set(None);
+            return Ok(());
+        };
+        if self.debugger_window.is_none() {
+            let video_subsystem = self.sdl_context.video()?;
+            let debugger_window = DebuggerWindow::build(&video_subsystem, self.renderer.window())?;
+            self.debugger_window_id.set(Some(debugger_window.id()));
+            self.debugger_window = Some(debugger_window);
+        }
+        match &mut self.debugger_window {
+            Some(debugger_window) => debugger_window.draw(view),
+            None => Ok(()),
+        }
+    }
+
+    fn show_rebinding(&mut self, rebinding: Option<&Rebinding>) {
+        match rebinding {
+            Some(rebinding) => {
+                let mut highlighted = [false; 16];
+                if let Some(key) = rebinding.current_key() {
+                    highlighted[key] = true;
+                }
+                self.renderer.set_keypad_overlay(Some(highlighted));
+                self.renderer.set_title(&rebinding.message);
+            }
+            None => {
+                self.renderer.set_keypad_overlay(None);
+                self.renderer.set_title(&self.title);
+            }
+        }
     }
 }
@@ -1,68 +1,792 @@
 use crate::audio::AudioDriver;
 use crate::chip8::Chip8;
+use crate::cli::RunArgs;
+use crate::config;
+use crate::database::RomInfo;
+use crate::debugger::{Debugger, DebuggerAction};
 use crate::display::DisplayDriver;
-use crate::keyboard::Keyboard;
+use crate::frontend::{
+    AudioSink, Frontend, Hotkey, InputSource, NullAudio, NullInput, NullVideo, VideoSink,
+};
+use crate::hud::Hud;
+use crate::input_movie::{InputMovie, MoviePlayer, MovieRecorder};
+use crate::keyboard::{Keyboard, SdlInput};
+use crate::rebind::{Rebinding, SaveTo};
+use crate::screenshot;
+use crate::settings::Settings;
+use crate::terminal::{TerminalInput, TerminalVideo};
+use crate::video_recorder::{VideoFormat, VideoRecorder};
+use crate::wav_recorder::WavRecorder;
+use std::path::PathBuf;
 use std::time::{Duration, Instant};
 
+// what the user sees the emulator through and controls it with
+type Frontends = (Box<dyn VideoSink>, Box<dyn InputSource>);
+
+// a whole emulated frame takes this long at normal speed
+const FRAME_INTERVAL: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+// fast-forward and slow motion only change how long the host waits between
+// emulated frames, so the timers and the sound keep in step with the cpu
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Speed {
+    Normal,
+    FastForward,
+    SlowMotion,
+}
+
+// what a paused game was asked to run this frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Advance {
+    Instruction,
+    Frame,
+}
+
 pub struct Emulator {
-    audio_driver: AudioDriver,
-    display_driver: DisplayDriver,
+    audio: Box<dyn AudioSink>,
+    video: Box<dyn VideoSink>,
+    input: Box<dyn InputSource>,
     chip8_processor: Chip8,
     keyboard: Keyboard,
+    instructions_per_frame: u32,
+    frame_limit: Option<u64>,
+    // emulated frames run so far, paused ones don't count
+    frames: u64,
+    throttle: bool,
+    // kept around for the things that can change while running, like the theme
+    settings: Settings,
+    video_recorder: Option<VideoRecorder>,
+    // fed on the emulated frame clock, unlike the speakers which go quiet
+    // whenever the game isn't running
+    audio_recorder: Option<WavRecorder>,
+    // the game is paused while this is going
+    rebinding: Option<Rebinding>,
+    // Some while the overlay is showing
+    hud: Option<Hud>,
+    // Some while the debugger window is open
+    debugger: Option<Debugger>,
+    paused: bool,
+    // how many of this frame's instructions have run. stepping and breakpoints
+    // can stop partway through a frame, the timers tick once all of them have
+    cycle: u32,
+    speed: Speed,
 }
 
 impl Emulator {
-    pub fn build() -> Result<Emulator, String> {
-        let audio_driver = AudioDriver::build();
-        let display_driver = DisplayDriver::build()?;
-        let mut chip8_processor = Chip8::new();
-        let (width, height) = display_driver.get_window_dimensions();
-        chip8_processor.initialize_pixels(height, width)?;
-        let keyboard = Keyboard::new();
+    pub fn build(args: &RunArgs) -> Result<Emulator, String> {
+        let mut settings = config::resolve_settings(args)?;
+        if let Some(rom_info) = &settings.rom_info {
+            Emulator::print_rom_info(rom_info, &settings);
+        }
+
+        let movie = match &settings.play_input {
+            Some(path) => Some(InputMovie::load(path)?),
+            None => None,
+        };
+        // replays need the same random numbers as the original run
+        if settings.seed.is_none() {
+            settings.seed = match &movie {
+                Some(movie) => movie.seed,
+                None if settings.record_input.is_some() => Some(rand::random()),
+                None => None,
+            };
+        }
+
+        let audio = Emulator::build_speakers(&settings);
+        let (video, input) = Emulator::build_frontend(&settings)?;
+        let input = Emulator::wrap_input(&settings, input, movie);
+
+        Emulator::with_frontends(&settings, video, audio, input)
+    }
+
+    fn build_frontend(settings: &Settings) -> Result<Frontends, String> {
+        if settings.headless {
+            return Ok((Box::new(NullVideo), Box::new(NullInput)));
+        }
+
+        match settings.frontend {
+            Frontend::Sdl => {
+                let display_driver = DisplayDriver::build(settings)?;
+                // no controller support isn't worth refusing to start over
+                let game_controller = display_driver
+                    .game_controller()
+                    .map_err(|e| eprintln!("warning: gamepads are not available: {}", e))
+                    .ok();
+                let input = SdlInput::new(
+                    display_driver.event_pump()?,
+                    game_controller,
+                    display_driver.keypad_area(),
+                    display_driver.debugger_window_id(),
+                );
+                Ok((Box::new(display_driver), Box::new(input)))
+            }
+            Frontend::Tui => {
+                // bindings first, a bad key name shouldn't leave the terminal in raw mode
+                let input = TerminalInput::with_bindings(&settings.keys)?;
+                let video = TerminalVideo::new(settings.palette)?;
+                Ok((Box::new(video), Box::new(input)))
+            }
+        }
+    }
+
+    fn wrap_input(
+        settings: &Settings,
+        mut input: Box<dyn InputSource>,
+        movie: Option<InputMovie>,
+    ) -> Box<dyn InputSource> {
+        if let Some(movie) = movie {
+            input = Box::new(MoviePlayer::new(input, movie));
+        }
+        if let Some(path) = &settings.record_input {
+            input = Box::new(MovieRecorder::new(input, path.clone(), settings.seed));
+        }
+        input
+    }
+
+    // no sound card (ci, headless servers) shouldn't stop the emulator from running
+    fn build_speakers(settings: &Settings) -> Box<dyn AudioSink> {
+        if !settings.audio || settings.headless {
+            return Box::new(NullAudio);
+        }
+
+        match AudioDriver::build(settings) {
+            Ok(audio_driver) => Box::new(audio_driver),
+            Err(e) => {
+                eprintln!(
+                    "warning: could not open an audio device ({}), running without sound",
+                    e
+                );
+                Box::new(NullAudio)
+            }
+        }
+    }
+
+    // stderr, stdout might be carrying a --record-video - stream
+    fn print_rom_info(rom_info: &RomInfo, settings: &Settings) {
+        eprintln!("{}", rom_info.display_name());
+        for (action, keypad_key) in &rom_info.keys {
+            let host_keys = match settings.keys.get(&format!("{:X}", keypad_key)) {
+                Some(host_keys) if !host_keys.is_empty() => host_keys.join("/"),
+                _ => String::from("unbound"),
+            };
+            eprintln!("  {}: keypad {:X} ({})", action, keypad_key, host_keys);
+        }
+    }
+
+    pub fn with_frontends(
+        settings: &Settings,
+        video: Box<dyn VideoSink>,
+        audio: Box<dyn AudioSink>,
+        input: Box<dyn InputSource>,
+    ) -> Result<Emulator, String> {
+        let chip8_processor = Emulator::build_chip8(settings);
+        let keyboard = Keyboard::build(settings)?;
+        let video_recorder = match &settings.record_video {
+            Some(path) => Some(VideoRecorder::create(
+                path,
+                settings.video_format,
+                settings.video_scale,
+            )?),
+            None => None,
+        };
+        let audio_recorder = match &settings.record_audio {
+            Some(path) => Some(WavRecorder::create(path, settings)?),
+            None => None,
+        };
 
         Ok(Emulator {
-            audio_driver,
-            display_driver,
+            audio,
+            video,
+            input,
             chip8_processor,
             keyboard,
+            instructions_per_frame: settings.instructions_per_frame,
+            frame_limit: settings.frames,
+            frames: 0,
+            // headless runs go as fast as they can
+            throttle: !settings.headless,
+            settings: settings.clone(),
+            video_recorder,
+            audio_recorder,
+            rebinding: None,
+            hud: settings.hud.then(Hud::new),
+            debugger: None,
+            paused: false,
+            cycle: 0,
+            speed: Speed::Normal,
         })
     }
 
+    fn build_chip8(settings: &Settings) -> Chip8 {
+        let mut chip8_processor = Chip8::new();
+        chip8_processor.set_quirks(settings.effective_quirks());
+        if let Some(seed) = settings.seed {
+            chip8_processor.set_seed(seed);
+        }
+        chip8_processor
+    }
+
     pub fn run(&mut self, rom: String) -> Result<(), String> {
-        self.chip8_processor.load_rom(rom)?;
+        self.chip8_processor.load_rom(rom.clone())?;
+        if self.settings.debugger {
+            // starts before the first instruction so breakpoints can be set
+            self.paused = true;
+            self.toggle_debugger();
+            self.paused = self.debugger.is_some();
+        }
 
-        let interval = Duration::from_nanos(1_000_000_000u64 / 60);
-        let mut last_tick = Instant::now();
+        let mut next_frame = Instant::now() + self.frame_interval();
 
         'running: loop {
-            if !self
-                .keyboard
-                .parse_keyboard_event(&mut self.display_driver.event_pump)
+            let frame_start = Instant::now();
+            let frames_before = self.frames;
+            let mut advance = None;
+            for hotkey in self.input.poll(&mut self.keyboard) {
+                match hotkey {
+                    Hotkey::Quit => break 'running,
+                    Hotkey::NextTheme => self.next_theme(),
+                    Hotkey::NextFilter => {
+                        self.settings.filter = self.settings.filter.next();
+                        self.video.set_filter(self.settings.filter);
+                        eprintln!("filter: {}", self.settings.filter.name());
+                    }
+                    Hotkey::Screenshot => match self.screenshot() {
+                        Ok(path) => eprintln!("saved {}", path.display()),
+                        Err(e) => eprintln!("warning: could not save a screenshot: {}", e),
+                    },
+                    Hotkey::ToggleFullscreen => {
+                        if let Err(e) = self.video.toggle_fullscreen() {
+                            eprintln!("warning: could not toggle fullscreen: {}", e);
+                        }
+                    }
+                    Hotkey::ToggleRecording => self.toggle_recording(),
+                    Hotkey::Rebind => self.toggle_rebinding(),
+                    Hotkey::ToggleKeypad => self.video.toggle_keypad(),
+                    Hotkey::ToggleHud => {
+                        if self.hud.take().is_some() {
+                            self.video.show_hud(None);
+                        } else {
+                            self.hud = Some(Hud::new());
+                        }
+                    }
+                    Hotkey::ToggleDebugger => self.toggle_debugger(),
+                    Hotkey::Pause => self.toggle_pause(),
+                    Hotkey::Step => advance = Some(Advance::Instruction),
+                    Hotkey::FrameAdvance => advance = Some(Advance::Frame),
+                    Hotkey::Reset => self.reset(&rom),
+                    Hotkey::ToggleFastForward => self.toggle_speed(Speed::FastForward),
+                    Hotkey::ToggleSlowMotion => self.toggle_speed(Speed::SlowMotion),
+                    Hotkey::DebuggerClick { column, row } => {
+                        let Some(debugger) = &mut self.debugger else {
+                            continue;
+                        };
+                        match debugger.click(column, row, &self.chip8_processor) {
+                            Some(DebuggerAction::TogglePause) => self.toggle_pause(),
+                            Some(DebuggerAction::Step) => advance = Some(Advance::Instruction),
+                            None => {}
+                        }
+                    }
+                    Hotkey::DebuggerScroll(rows) => {
+                        if let Some(debugger) = &mut self.debugger {
+                            debugger.scroll(rows);
+                        }
+                    }
+                }
+            }
+
+            let mut instructions = 0;
+            if self.rebinding.is_some() {
+                self.rebind_step();
+            } else if !self.paused || advance.is_some() {
+                self.chip8_processor
+                    .handle_key_events(&self.keyboard.take_events());
+                if self.paused {
+                    self.resume_debugger();
+                }
+                // a frame advance finishes the frame a step might have started
+                let count = match (self.paused, advance) {
+                    (true, Some(Advance::Instruction)) => 1,
+                    _ => self.instructions_per_frame - self.cycle,
+                };
+                instructions = self.run_instructions(count);
+            }
+
+            if let Some(debugger) = &mut self.debugger {
+                debugger.frame_done(&self.chip8_processor.take_written());
+                let view = debugger.view(&self.chip8_processor, self.paused);
+                self.video.show_debugger(Some(&view))?;
+            }
+
+            self.video.show_keypad_state(self.keyboard.get_state());
+            if let Some(hud) = &self.hud {
+                let lines = hud.lines(&self.chip8_processor, &self.quirks_name());
+                self.video.show_hud(Some(&lines));
+            }
+            if self.video.draw(&self.chip8_processor)? {
+                self.chip8_processor.vram_changed = false;
+            }
+
+            if self.chip8_processor.get_sound_timer() > 0
+                && self.rebinding.is_none()
+                && !self.paused
             {
+                self.audio.play();
+            } else {
+                self.audio.stop();
+            }
+
+            // paused, stepping or stopped at a breakpoint isn't an emulated frame
+            if let Some(hud) = self.hud.as_mut().filter(|_| self.frames > frames_before) {
+                hud.frame_done(instructions, frame_start.elapsed());
+            }
+
+            if self.frame_limit.is_some_and(|limit| self.frames >= limit) {
                 break 'running;
             }
 
-            self.chip8_processor
-                .handle_next_instruction(&mut self.keyboard, &mut self.display_driver.event_pump);
-            if self.chip8_processor.vram_changed {
-                self.display_driver.renderer.draw(&mut self.chip8_processor);
+            if self.throttle {
+                let interval = self.frame_interval();
+                let now = Instant::now();
+                if next_frame > now {
+                    ::std::thread::sleep(next_frame - now);
+                    next_frame += interval;
+                } else {
+                    // fell behind, don't try to catch up
+                    next_frame = now + interval;
+                }
+            }
+        }
+
+        if let Some(video_recorder) = self.video_recorder.take() {
+            video_recorder.finish()?;
+        }
+        Ok(())
+    }
+
+    // runs up to this many instructions, less if one is on a breakpoint. the
+    // timers tick after every instructions_per_frame of them however they're
+    // split up, so stepping and breakpoints don't speed them up or slow them down
+    fn run_instructions(&mut self, count: u32) -> u32 {
+        for ran in 0..count {
+            let program_counter = self.chip8_processor.get_program_counter();
+            if let Some(debugger) = &mut self.debugger {
+                if debugger.should_break(program_counter) {
+                    self.paused = true;
+                    eprintln!("breakpoint at {:04X}", program_counter);
+                    return ran;
+                }
             }
-            let now = Instant::now();
-            if now.duration_since(last_tick) >= interval {
+
+            self.chip8_processor.handle_next_instruction(&self.keyboard);
+            self.cycle += 1;
+            if self.cycle == self.instructions_per_frame {
+                self.cycle = 0;
+                self.frames += 1;
                 self.chip8_processor.decrement_sound_timer();
                 self.chip8_processor.decrement_delay_timer();
-                last_tick = now;
+                self.record_video_frame();
+                self.record_audio_frame();
+                self.input.frame_done(&self.keyboard);
+                self.keyboard.next_frame();
             }
+        }
+        count
+    }
 
-            if self.chip8_processor.get_sound_timer() > 0 {
-                self.audio_driver.play();
-            } else {
-                self.audio_driver.stop();
+    fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        if self.paused {
+            eprintln!(
+                "paused at {:04X}",
+                self.chip8_processor.get_program_counter()
+            );
+        } else {
+            self.resume_debugger();
+            eprintln!("resumed");
+        }
+    }
+
+    fn frame_interval(&self) -> Duration {
+        match self.speed {
+            Speed::Normal => FRAME_INTERVAL,
+            Speed::FastForward => FRAME_INTERVAL / self.settings.fast_forward,
+            Speed::SlowMotion => FRAME_INTERVAL * self.settings.slow_motion,
+        }
+    }
+
+    // pressing the key for the speed it's already at goes back to normal
+    fn toggle_speed(&mut self, speed: Speed) {
+        self.speed = if self.speed == speed {
+            Speed::Normal
+        } else {
+            speed
+        };
+        match self.speed {
+            Speed::Normal => eprintln!("normal speed"),
+            Speed::FastForward => eprintln!("fast-forward {}x", self.settings.fast_forward),
+            Speed::SlowMotion => eprintln!("slow motion 1/{}x", self.settings.slow_motion),
+        }
+    }
+
+    // a fresh machine with the rom loaded again. pausing, the speed and the
+    // debugger's breakpoints carry on as they were
+    fn reset(&mut self, rom: &str) {
+        let mut chip8_processor = Emulator::build_chip8(&self.settings);
+        if let Err(e) = chip8_processor.load_rom(rom.to_string()) {
+            eprintln!("warning: could not reset: {}", e);
+            return;
+        }
+        chip8_processor.set_write_tracking(self.debugger.is_some());
+        // the old picture has to go even though nothing has been drawn yet
+        chip8_processor.vram_changed = true;
+        self.chip8_processor = chip8_processor;
+        self.cycle = 0;
+        eprintln!("reset");
+    }
+
+    // carrying on from a breakpoint shouldn't stop on it straight away
+    fn resume_debugger(&mut self) {
+        if let Some(debugger) = &mut self.debugger {
+            debugger.resume(self.chip8_processor.get_program_counter());
+        }
+    }
+
+    // the game keeps whatever pause state it had when the window closes
+    fn toggle_debugger(&mut self) {
+        if self.debugger.take().is_some() {
+            self.chip8_processor.set_write_tracking(false);
+            if let Err(e) = self.video.show_debugger(None) {
+                eprintln!("warning: could not close the debugger: {}", e);
+            }
+            return;
+        }
+
+        let debugger = Debugger::new();
+        let view = debugger.view(&self.chip8_processor, self.paused);
+        match self.video.show_debugger(Some(&view)) {
+            Ok(()) => {
+                self.chip8_processor.set_write_tracking(true);
+                self.debugger = Some(debugger);
             }
+            Err(e) => eprintln!("warning: could not open the debugger: {}", e),
+        }
+    }
 
-            ::std::thread::sleep(Duration::new(0, 1_000_000_000u32 / 1000));
+    fn record_video_frame(&mut self) {
+        let Some(video_recorder) = self.video_recorder.as_mut() else {
+            return;
+        };
+        // give up on the recording rather than the whole emulator
+        if let Err(e) = video_recorder.record(&self.chip8_processor.vram, &self.settings.palette) {
+            eprintln!("warning: stopped recording video: {}", e);
+            self.video_recorder = None;
         }
+    }
 
-        Ok(())
+    fn record_audio_frame(&mut self) {
+        if let Some(audio_recorder) = &mut self.audio_recorder {
+            match self.chip8_processor.get_sound_timer() > 0 {
+                true => audio_recorder.play(),
+                false => audio_recorder.stop(),
+            }
+        }
+    }
+
+    // stops a recording, or starts one next to the screenshots
+    fn toggle_recording(&mut self) {
+        if let Some(video_recorder) = self.video_recorder.take() {
+            match video_recorder.finish() {
+                Ok(()) => eprintln!("recording stopped"),
+                Err(e) => eprintln!("warning: could not finish the video: {}", e),
+            }
+            return;
+        }
+
+        let format = self.settings.video_format.unwrap_or(VideoFormat::Gif);
+        let started = screenshot::timestamped_path(
+            &self.settings.screenshot_dir,
+            self.settings.rom_name.as_deref().unwrap_or("chip8"),
+            format.extension(),
+        )
+        .and_then(|path| {
+            let video_recorder =
+                VideoRecorder::create(&path, Some(format), self.settings.video_scale)?;
+            Ok((path, video_recorder))
+        });
+        match started {
+            Ok((path, video_recorder)) => {
+                eprintln!("recording to {}", path.display());
+                self.video_recorder = Some(video_recorder);
+            }
+            Err(e) => eprintln!("warning: could not start recording: {}", e),
+        }
+    }
+
+    fn toggle_rebinding(&mut self) {
+        if self.rebinding.take().is_some() {
+            self.keyboard.stop_capture();
+            self.video.show_rebinding(None);
+            eprintln!("rebinding cancelled");
+            return;
+        }
+
+        let rebinding = Rebinding::new();
+        self.keyboard.start_capture();
+        self.video.show_rebinding(Some(&rebinding));
+        eprintln!("{}", rebinding.message);
+        self.rebinding = Some(rebinding);
+    }
+
+    // hands whatever was pressed this frame to the rebinding screen
+    fn rebind_step(&mut self) {
+        let (Some(rebinding), Some(input)) =
+            (self.rebinding.as_mut(), self.keyboard.take_captured())
+        else {
+            return;
+        };
+        let save_to = rebinding.offer(input);
+        self.video.show_rebinding(Some(rebinding));
+        eprintln!("{}", rebinding.message);
+        if let Some(save_to) = save_to {
+            self.finish_rebinding(save_to);
+        }
+    }
+
+    fn finish_rebinding(&mut self, save_to: SaveTo) {
+        let Some(rebinding) = self.rebinding.take() else {
+            return;
+        };
+        self.keyboard.stop_capture();
+        self.video.show_rebinding(None);
+
+        rebinding.apply(&mut self.settings);
+        if let Err(e) = self.keyboard.set_bindings(&self.settings) {
+            eprintln!("warning: could not use the new bindings: {}", e);
+            return;
+        }
+
+        let settings = &self.settings;
+        let rom_sha1 = match save_to {
+            SaveTo::Global => None,
+            SaveTo::Rom => settings.rom_sha1.as_deref(),
+        };
+        let saved = match &settings.config_path {
+            Some(path) => config::save_bindings(path, rom_sha1, &settings.keys, &settings.gamepad)
+                .map(|_| path.clone()),
+            None => Err(String::from("there's no config file to save to")),
+        };
+        match saved {
+            Ok(path) => eprintln!("bindings saved to {}", path.display()),
+            Err(e) => eprintln!("warning: could not save the bindings: {}", e),
+        }
+    }
+
+    fn quirks_name(&self) -> String {
+        match self.settings.custom_quirks {
+            Some(_) => String::from("CUSTOM"),
+            None => format!("{:?}", self.settings.quirks).to_uppercase(),
+        }
+    }
+
+    // saves what's on screen right now in the configured format
+    pub fn screenshot(&self) -> Result<PathBuf, String> {
+        screenshot::save(
+            &self.settings.screenshot_dir,
+            self.settings.rom_name.as_deref().unwrap_or("chip8"),
+            &self.chip8_processor.vram,
+            &self.settings.palette,
+            self.settings.screenshot_format,
+            self.settings.screenshot_scale,
+        )
+    }
+
+    // a theme picked at runtime sticks to the rom for next time
+    fn next_theme(&mut self) {
+        let theme = self.settings.theme.next();
+        self.settings.theme = theme;
+        self.settings.palette = theme.palette();
+        self.video.set_palette(&self.settings.palette);
+        eprintln!("theme: {}", theme.name());
+
+        let settings = &self.settings;
+        if let (Some(path), Some(rom_sha1)) = (&settings.config_path, &settings.rom_sha1) {
+            if let Err(e) = config::save_rom_theme(path, rom_sha1, theme) {
+                eprintln!("warning: could not save the theme: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    struct CountingVideo {
+        draws: Rc<Cell<u32>>,
+    }
+
+    impl VideoSink for CountingVideo {
+        fn draw(&mut self, chip8: &Chip8) -> Result<bool, String> {
+            if chip8.vram_changed {
+                self.draws.set(self.draws.get() + 1);
+            }
+            Ok(true)
+        }
+    }
+
+    struct QuitAfter {
+        polls_left: u32,
+    }
+
+    impl InputSource for QuitAfter {
+        fn poll(&mut self, _keyboard: &mut Keyboard) -> Vec<Hotkey> {
+            if self.polls_left == 0 {
+                return vec![Hotkey::Quit];
+            }
+            self.polls_left -= 1;
+            Vec::new()
+        }
+    }
+
+    // each poll sets the keypad to a mask and presses some hotkeys, then it quits
+    struct Scripted {
+        polls: Vec<(u16, Vec<Hotkey>)>,
+    }
+
+    impl InputSource for Scripted {
+        fn poll(&mut self, keyboard: &mut Keyboard) -> Vec<Hotkey> {
+            if self.polls.is_empty() {
+                return vec![Hotkey::Quit];
+            }
+            let (mask, hotkeys) = self.polls.remove(0);
+            keyboard.set_state(std::array::from_fn(|key| mask & (1 << key) != 0));
+            hotkeys
+        }
+    }
+
+    #[test]
+    fn run_loop_with_mock_frontends_test() {
+        // 00E0 (clear screen) then 1200 (jump back to the start)
+        let rom_path = std::env::temp_dir().join("chip8_run_loop_with_mock_frontends_test.ch8");
+        std::fs::write(&rom_path, [0x00, 0xE0, 0x12, 0x00]).unwrap();
+
+        let settings = Settings {
+            instructions_per_frame: 1,
+            headless: true,
+            ..Settings::default()
+        };
+        let draws = Rc::new(Cell::new(0));
+        let mut emulator = Emulator::with_frontends(
+            &settings,
+            Box::new(CountingVideo {
+                draws: draws.clone(),
+            }),
+            Box::new(NullAudio),
+            Box::new(QuitAfter { polls_left: 4 }),
+        )
+        .unwrap();
+
+        emulator
+            .run(rom_path.to_string_lossy().into_owned())
+            .unwrap();
+
+        // one instruction a frame: clear screen on frames 1 and 3, the jump on 2 and 4
+        assert_eq!(2, draws.get());
+    }
+
+    #[test]
+    fn frame_limit_counts_emulated_frames_test() {
+        // 7001 (add 1 to v0) then 1200 (jump back to the start)
+        let rom_path =
+            std::env::temp_dir().join("chip8_frame_limit_counts_emulated_frames_test.ch8");
+        std::fs::write(&rom_path, [0x70, 0x01, 0x12, 0x00]).unwrap();
+
+        let settings = Settings {
+            instructions_per_frame: 2,
+            frames: Some(3),
+            headless: true,
+            ..Settings::default()
+        };
+        // a frame, two polls spent paused, then frames until the limit. one poll
+        // is to spare in case the limit doesn't stop it
+        let polls = vec![
+            (0, vec![]),
+            (0, vec![Hotkey::Pause]),
+            (0, vec![]),
+            (0, vec![Hotkey::Pause]),
+            (0, vec![]),
+            (0, vec![]),
+        ];
+        let mut emulator = Emulator::with_frontends(
+            &settings,
+            Box::new(NullVideo),
+            Box::new(NullAudio),
+            Box::new(Scripted { polls }),
+        )
+        .unwrap();
+        emulator
+            .run(rom_path.to_string_lossy().into_owned())
+            .unwrap();
+
+        assert_eq!(3, emulator.frames);
+        assert_eq!(3, emulator.chip8_processor.get_registers()[0]);
+    }
+
+    #[test]
+    fn paused_frames_stay_out_of_movies_test() {
+        // F00A (wait for a key into v0) then 1202 (jump to itself)
+        let rom_path = std::env::temp_dir().join("chip8_paused_frames_stay_out_of_movies_test.ch8");
+        let movie_path =
+            std::env::temp_dir().join("chip8_paused_frames_stay_out_of_movies_test.txt");
+        std::fs::write(&rom_path, [0xF0, 0x0A, 0x12, 0x02]).unwrap();
+        let rom = rom_path.to_string_lossy().into_owned();
+
+        let settings = Settings {
+            instructions_per_frame: 1,
+            headless: true,
+            record_input: Some(movie_path.clone()),
+            ..Settings::default()
+        };
+        // a frame, three paused ones with a reset in the middle, then key 5 tapped
+        let script = Scripted {
+            polls: vec![
+                (0, vec![]),
+                (0, vec![Hotkey::Pause]),
+                (0, vec![]),
+                (0, vec![Hotkey::Reset]),
+                (0, vec![]),
+                (0, vec![Hotkey::Pause]),
+                (1 << 5, vec![]),
+                (0, vec![]),
+            ],
+        };
+        let input = Emulator::wrap_input(&settings, Box::new(script), None);
+        let mut emulator =
+            Emulator::with_frontends(&settings, Box::new(NullVideo), Box::new(NullAudio), input)
+                .unwrap();
+        emulator.run(rom.clone()).unwrap();
+        assert_eq!(5, emulator.chip8_processor.get_registers()[0]);
+        drop(emulator);
+
+        let movie = InputMovie::load(&movie_path).unwrap();
+        assert_eq!(4, movie.length);
+        assert_eq!(
+            "# chip-8 input movie\n2 0020\n3 0000\nreset 1\nlength 4\n",
+            movie.to_text()
+        );
+
+        let settings = Settings {
+            record_input: None,
+            ..settings
+        };
+        let input = Emulator::wrap_input(&settings, Box::new(NullInput), Some(movie));
+        let mut emulator =
+            Emulator::with_frontends(&settings, Box::new(NullVideo), Box::new(NullAudio), input)
+                .unwrap();
+        emulator.run(rom).unwrap();
+        assert_eq!(5, emulator.chip8_processor.get_registers()[0]);
     }
 }
@@ -0,0 +1,278 @@
+use crate::phosphor::blend;
+use crate::settings::Rgb;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+// all of these run on the cpu, so they work the same over remote desktop or
+// on a machine with no gpu at all
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Filter {
+    // sharp square pixels
+    None,
+    // darkened lines between pixel rows, like a crt
+    Scanlines,
+    // darkened lines between rows and columns, like an lcd
+    Grid,
+    // lit pixels glow into their surroundings
+    Bloom,
+    // pixel art scaler that rounds off diagonals (also known as epx)
+    Scale2x,
+    // like scale2x but blends the corners instead of copying them
+    #[serde(rename = "smooth-epx")]
+    SmoothEpx,
+}
+
+const FILTERS: [Filter; 6] = [
+    Filter::None,
+    Filter::Scanlines,
+    Filter::Grid,
+    Filter::Bloom,
+    Filter::Scale2x,
+    Filter::SmoothEpx,
+];
+
+// an rgb image, row by row
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Rgb>,
+}
+
+impl Frame {
+    pub fn new(width: usize, height: usize, pixels: Vec<Rgb>) -> Frame {
+        Frame {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    // out of bounds reads get the nearest edge pixel
+    fn get(&self, x: isize, y: isize) -> Rgb {
+        let x = x.clamp(0, self.width as isize - 1) as usize;
+        let y = y.clamp(0, self.height as isize - 1) as usize;
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn scale_nearest(&self, factor: usize) -> Frame {
+        let width = self.width * factor;
+        let height = self.height * factor;
+        let pixels = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| self.pixels[(y / factor) * self.width + x / factor])
+            .collect();
+        Frame::new(width, height, pixels)
+    }
+}
+
+impl Filter {
+    // factor is how many screen pixels a chip-8 pixel can be blown up to. the
+    // crt style filters need room to draw their lines in, the pixel art
+    // scalers always come out at 2x and leave the rest to the renderer
+    pub fn apply(self, frame: &Frame, factor: usize) -> Frame {
+        match self {
+            Filter::None => frame.clone(),
+            Filter::Scanlines => scanlines(&frame.scale_nearest(factor), factor),
+            Filter::Grid => grid(&frame.scale_nearest(factor), factor),
+            Filter::Bloom => bloom(&frame.scale_nearest(factor), factor),
+            Filter::Scale2x => scale2x(frame, epx_corner),
+            Filter::SmoothEpx => scale2x(frame, smooth_corner),
+        }
+    }
+
+    // for the hotkey, wraps back around to no filter
+    pub fn next(self) -> Filter {
+        let index = FILTERS
+            .iter()
+            .position(|filter| *filter == self)
+            .unwrap_or(0);
+        FILTERS[(index + 1) % FILTERS.len()]
+    }
+
+    pub fn name(self) -> String {
+        self.to_possible_value()
+            .map(|value| value.get_name().to_string())
+            .unwrap_or_default()
+    }
+}
+
+fn darken((r, g, b): Rgb, amount: f32) -> Rgb {
+    blend((r, g, b), (0, 0, 0), amount)
+}
+
+// the bottom third of every pixel row is dimmed
+fn scanlines(frame: &Frame, factor: usize) -> Frame {
+    if factor < 2 {
+        return frame.clone();
+    }
+
+    let dark_rows = (factor / 3).max(1);
+    let mut output = frame.clone();
+    for y in 0..frame.height {
+        if y % factor >= factor - dark_rows {
+            let row = &mut output.pixels[y * frame.width..(y + 1) * frame.width];
+            row.iter_mut()
+                .for_each(|pixel| *pixel = darken(*pixel, 0.5));
+        }
+    }
+    output
+}
+
+fn grid(frame: &Frame, factor: usize) -> Frame {
+    if factor < 2 {
+        return frame.clone();
+    }
+
+    let mut output = frame.clone();
+    for (index, pixel) in output.pixels.iter_mut().enumerate() {
+        let (x, y) = (index % frame.width, index / frame.width);
+        if x % factor == factor - 1 || y % factor == factor - 1 {
+            *pixel = darken(*pixel, 0.4);
+        }
+    }
+    output
+}
+
+// a blurred copy of the frame added on top of itself. the blur is a box blur
+// done with running sums, so it costs the same at any radius
+fn bloom(frame: &Frame, factor: usize) -> Frame {
+    let radius = factor.max(1) as isize;
+    let blurred = transpose(&blur_rows(&transpose(&blur_rows(frame, radius)), radius));
+
+    let add = |base: u8, glow: u8| base.saturating_add((glow as f32 * 0.6) as u8);
+    let pixels = frame
+        .pixels
+        .iter()
+        .zip(&blurred.pixels)
+        .map(|((r, g, b), (glow_r, glow_g, glow_b))| {
+            (add(*r, *glow_r), add(*g, *glow_g), add(*b, *glow_b))
+        })
+        .collect();
+    Frame::new(frame.width, frame.height, pixels)
+}
+
+fn blur_rows(frame: &Frame, radius: isize) -> Frame {
+    let size = (radius * 2 + 1) as u32;
+    let mut pixels = Vec::with_capacity(frame.pixels.len());
+    for y in 0..frame.height as isize {
+        let mut sum = [0u32; 3];
+        for x in -radius..=radius {
+            let (r, g, b) = frame.get(x, y);
+            sum = [sum[0] + r as u32, sum[1] + g as u32, sum[2] + b as u32];
+        }
+        for x in 0..frame.width as isize {
+            pixels.push((
+                (sum[0] / size) as u8,
+                (sum[1] / size) as u8,
+                (sum[2] / size) as u8,
+            ));
+            let (r, g, b) = frame.get(x + radius + 1, y);
+            let (old_r, old_g, old_b) = frame.get(x - radius, y);
+            sum = [
+                sum[0] + r as u32 - old_r as u32,
+                sum[1] + g as u32 - old_g as u32,
+                sum[2] + b as u32 - old_b as u32,
+            ];
+        }
+    }
+    Frame::new(frame.width, frame.height, pixels)
+}
+
+fn transpose(frame: &Frame) -> Frame {
+    let pixels = (0..frame.width)
+        .flat_map(|x| (0..frame.height).map(move |y| (x, y)))
+        .map(|(x, y)| frame.pixels[y * frame.width + x])
+        .collect();
+    Frame::new(frame.height, frame.width, pixels)
+}
+
+// picks one output corner. center is the pixel being scaled, side_a and
+// side_b are the two neighbours touching that corner, opposite_a and
+// opposite_b the neighbours on the other side of center from them
+type Corner = fn(center: Rgb, side_a: Rgb, side_b: Rgb, opposite_a: Rgb, opposite_b: Rgb) -> Rgb;
+
+fn epx_corner(center: Rgb, side_a: Rgb, side_b: Rgb, opposite_a: Rgb, opposite_b: Rgb) -> Rgb {
+    if side_a == side_b && side_a != opposite_b && side_b != opposite_a {
+        side_a
+    } else {
+        center
+    }
+}
+
+// same test as epx, but with a tolerance for nearly equal colours (the phosphor
+// fade makes plenty of those) and a 3:1 blend instead of a hard copy
+fn smooth_corner(center: Rgb, side_a: Rgb, side_b: Rgb, opposite_a: Rgb, opposite_b: Rgb) -> Rgb {
+    if similar(side_a, side_b) && !similar(side_a, opposite_b) && !similar(side_b, opposite_a) {
+        blend(center, side_a, 0.75)
+    } else {
+        center
+    }
+}
+
+fn similar(a: Rgb, b: Rgb) -> bool {
+    let luma = |(r, g, b): Rgb| 0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32;
+    (luma(a) - luma(b)).abs() < 24.0
+}
+
+fn scale2x(frame: &Frame, corner: Corner) -> Frame {
+    let width = frame.width * 2;
+    let mut pixels = vec![(0, 0, 0); width * frame.height * 2];
+    for y in 0..frame.height as isize {
+        for x in 0..frame.width as isize {
+            let center = frame.get(x, y);
+            let up = frame.get(x, y - 1);
+            let down = frame.get(x, y + 1);
+            let left = frame.get(x - 1, y);
+            let right = frame.get(x + 1, y);
+
+            let (ox, oy) = (x as usize * 2, y as usize * 2);
+            pixels[oy * width + ox] = corner(center, left, up, right, down);
+            pixels[oy * width + ox + 1] = corner(center, up, right, down, left);
+            pixels[(oy + 1) * width + ox] = corner(center, down, left, up, right);
+            pixels[(oy + 1) * width + ox + 1] = corner(center, right, down, left, up);
+        }
+    }
+    Frame::new(width, frame.height * 2, pixels)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    const ON: Rgb = (0xFF, 0xFF, 0xFF);
+    const OFF: Rgb = (0x00, 0x00, 0x00);
+
+    #[test]
+    fn scale2x_rounds_off_diagonals_test() {
+        // a staircase, the step in the top right gets filled in
+        #[rustfmt::skip]
+        let frame = Frame::new(2, 2, vec![
+            ON, OFF,
+            ON, ON,
+        ]);
+
+        let scaled = Filter::Scale2x.apply(&frame, 1);
+
+        assert_eq!((4, 4), (scaled.width, scaled.height));
+        #[rustfmt::skip]
+        assert_eq!(vec![
+            ON, ON, OFF, OFF,
+            ON, ON, ON, OFF,
+            ON, ON, ON, ON,
+            ON, ON, ON, ON,
+        ], scaled.pixels);
+    }
+
+    #[test]
+    fn scanlines_darken_bottom_of_rows_test() {
+        let frame = Frame::new(2, 2, vec![ON; 4]);
+
+        let lined = Filter::Scanlines.apply(&frame, 3);
+        assert_eq!((6, 6), (lined.width, lined.height));
+        assert_eq!(ON, lined.pixels[0]);
+        assert_eq!((0x80, 0x80, 0x80), lined.pixels[2 * 6]);
+    }
+}
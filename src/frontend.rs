@@ -0,0 +1,121 @@
+use crate::chip8::Chip8;
+use crate::debugger::ViewText;
+use crate::filters::Filter;
+use crate::keyboard::Keyboard;
+use crate::palette::Palette;
+use crate::rebind::Rebinding;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+// where the picture goes and the keys come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Frontend {
+    // a window
+    Sdl,
+    // the terminal, for running over ssh
+    Tui,
+}
+
+// the emulator only talks to the outside world through these three traits,
+// so sdl, null and whatever else can be swapped in without touching the run loop
+pub trait VideoSink {
+    // called once per emulated frame. returns true if the frame made it to the
+    // screen, so the emulator knows it can clear vram_changed
+    fn draw(&mut self, chip8: &Chip8) -> Result<bool, String>;
+    // the next draw should use these colours, even if vram hasn't changed
+    fn set_palette(&mut self, _palette: &Palette) {}
+    fn set_filter(&mut self, _filter: Filter) {}
+    fn toggle_fullscreen(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+    // shows the rebinding prompt over the game, None puts the game back
+    fn show_rebinding(&mut self, _rebinding: Option<&Rebinding>) {}
+    // called every frame with what's held, for the on-screen keypad
+    fn show_keypad_state(&mut self, _state: &[bool; 16]) {}
+    fn toggle_keypad(&mut self) {}
+    // lines of text over the game, None hides them
+    fn show_hud(&mut self, _lines: Option<&[String]>) {}
+    // opens the debugger window if it isn't already and shows this in it,
+    // None closes it
+    fn show_debugger(&mut self, view: Option<&[ViewText]>) -> Result<(), String> {
+        match view {
+            Some(_) => Err(String::from("the debugger needs the sdl frontend")),
+            None => Ok(()),
+        }
+    }
+}
+
+// exactly one of these per frame. the speakers get one per pass of the run
+// loop, a recording one per emulated 60Hz frame
+pub trait AudioSink {
+    fn play(&mut self);
+    fn stop(&mut self);
+}
+
+// requests from the user that aren't keypad presses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hotkey {
+    Quit,
+    NextTheme,
+    NextFilter,
+    ToggleFullscreen,
+    Screenshot,
+    ToggleRecording,
+    // starts rebinding the keypad, or cancels it
+    Rebind,
+    // shows or hides the on-screen keypad
+    ToggleKeypad,
+    ToggleHud,
+    // opens or closes the debugger window
+    ToggleDebugger,
+    // pauses the game, or carries on from where it was paused
+    Pause,
+    // runs a single instruction while paused
+    Step,
+    // runs the rest of the frame while paused
+    FrameAdvance,
+    // starts the rom again from scratch
+    Reset,
+    ToggleFastForward,
+    ToggleSlowMotion,
+    // a click in the debugger window, in text cells
+    DebuggerClick { column: usize, row: usize },
+    // scrolls the debugger's memory view by this many rows, negative is up
+    DebuggerScroll(i32),
+}
+
+pub trait InputSource {
+    // updates the keypad and returns any hotkeys pressed since the last poll
+    fn poll(&mut self, keyboard: &mut Keyboard) -> Vec<Hotkey>;
+
+    // called each time an emulated frame finishes, with the keypad as that
+    // frame saw it. frames spent paused or rebinding don't count
+    fn frame_done(&mut self, _keyboard: &Keyboard) {}
+}
+
+// draws nothing
+pub struct NullVideo;
+
+impl VideoSink for NullVideo {
+    fn draw(&mut self, _chip8: &Chip8) -> Result<bool, String> {
+        Ok(true)
+    }
+}
+
+pub struct NullAudio;
+
+impl AudioSink for NullAudio {
+    fn play(&mut self) {}
+
+    fn stop(&mut self) {}
+}
+
+// never presses anything and never asks to quit
+pub struct NullInput;
+
+impl InputSource for NullInput {
+    fn poll(&mut self, _keyboard: &mut Keyboard) -> Vec<Hotkey> {
+        Vec::new()
+    }
+}
@@ -0,0 +1,92 @@
+use crate::chip8::Chip8;
+use std::time::{Duration, Instant};
+
+// what the overlay shows. speeds are measured over a second at a time, the
+// machine state is whatever it is this frame
+pub struct Hud {
+    frames: u32,
+    instructions: u64,
+    busy: Duration,
+    since: Instant,
+    fps: u32,
+    ips: u64,
+    // how long the host spent on a frame on average, not counting the sleep
+    frame_time: Duration,
+}
+
+impl Hud {
+    pub fn new() -> Hud {
+        Hud {
+            frames: 0,
+            instructions: 0,
+            busy: Duration::ZERO,
+            since: Instant::now(),
+            fps: 0,
+            ips: 0,
+            frame_time: Duration::ZERO,
+        }
+    }
+
+    // after every emulated frame
+    pub fn frame_done(&mut self, instructions: u32, busy: Duration) {
+        self.frames += 1;
+        self.instructions += instructions as u64;
+        self.busy += busy;
+
+        let elapsed = self.since.elapsed();
+        if elapsed >= Duration::from_secs(1) {
+            self.fps = (self.frames as f64 / elapsed.as_secs_f64()).round() as u32;
+            self.ips = (self.instructions as f64 / elapsed.as_secs_f64()).round() as u64;
+            self.frame_time = self.busy / self.frames;
+            self.frames = 0;
+            self.instructions = 0;
+            self.busy = Duration::ZERO;
+            self.since = Instant::now();
+        }
+    }
+
+    pub fn lines(&self, chip8: &Chip8, quirks: &str) -> Vec<String> {
+        let registers: Vec<String> = chip8
+            .get_registers()
+            .iter()
+            .map(|value| format!("{:02X}", value))
+            .collect();
+        vec![
+            format!(
+                "FPS {}  IPS {}  FRAME {:.1}MS",
+                self.fps,
+                self.ips,
+                self.frame_time.as_secs_f64() * 1000.0
+            ),
+            format!("QUIRKS {}", quirks),
+            format!(
+                "PC {:04X}  I {:04X}  SP {}",
+                chip8.get_program_counter(),
+                chip8.get_index_register(),
+                chip8.get_stack().len()
+            ),
+            format!(
+                "DT {:02X}  ST {:02X}",
+                chip8.get_delay_timer(),
+                chip8.get_sound_timer()
+            ),
+            format!("V0-7 {}", registers[..8].join(" ")),
+            format!("V8-F {}", registers[8..].join(" ")),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn hud_lines_test() {
+        let lines = Hud::new().lines(&Chip8::new(), "CHIP8");
+
+        assert_eq!("QUIRKS CHIP8", lines[1]);
+        assert_eq!("PC 0200  I 0000  SP 0", lines[2]);
+        assert_eq!("V8-F 00 00 00 00 00 00 00 00", lines[5]);
+    }
+}
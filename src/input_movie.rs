@@ -0,0 +1,222 @@
+use crate::frontend::{Hotkey, InputSource};
+use crate::keyboard::Keyboard;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// the keypad state for every emulated frame, so a run can be replayed exactly.
+// stored as text, one line per change:
+//
+//   seed 1234
+//   0 0000
+//   120 0010
+//   135 0000
+//   reset 300
+//   length 600
+//
+// each change is the frame number and a mask of the held keys, bit n for key n.
+// a reset happens just before the frame it names. frames spent paused don't
+// count, only the ones the game actually ran
+#[derive(Debug, Default, PartialEq)]
+pub struct InputMovie {
+    // cxnn has to roll the same numbers for the replay to match
+    pub seed: Option<u64>,
+    changes: Vec<(u64, u16)>,
+    resets: Vec<u64>,
+    pub length: u64,
+}
+
+impl InputMovie {
+    pub fn load(path: &Path) -> Result<InputMovie, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Error opening {}: {}", path.display(), e))?;
+        InputMovie::parse(&contents).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn parse(contents: &str) -> Result<InputMovie, String> {
+        let mut movie = InputMovie::default();
+        for (line_number, line) in contents.lines().enumerate() {
+            let invalid = || format!("line {}: can't read '{}'", line_number + 1, line);
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+                ["seed", seed] => movie.seed = Some(seed.parse().map_err(|_| invalid())?),
+                ["length", length] => movie.length = length.parse().map_err(|_| invalid())?,
+                ["reset", frame] => {
+                    let frame: u64 = frame.parse().map_err(|_| invalid())?;
+                    if movie.resets.last().is_some_and(|last| *last >= frame) {
+                        return Err(format!("line {}: resets are out of order", line_number + 1));
+                    }
+                    movie.resets.push(frame);
+                }
+                [frame, mask] => {
+                    let frame: u64 = frame.parse().map_err(|_| invalid())?;
+                    let mask = u16::from_str_radix(mask, 16).map_err(|_| invalid())?;
+                    if movie.changes.last().is_some_and(|(last, _)| *last >= frame) {
+                        return Err(format!("line {}: frames are out of order", line_number + 1));
+                    }
+                    movie.changes.push((frame, mask));
+                }
+                _ => return Err(invalid()),
+            }
+        }
+
+        Ok(movie)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut output = String::from("# chip-8 input movie\n");
+        if let Some(seed) = self.seed {
+            output += &format!("seed {}\n", seed);
+        }
+        for (frame, mask) in &self.changes {
+            output += &format!("{} {:04x}\n", frame, mask);
+        }
+        for frame in &self.resets {
+            output += &format!("reset {}\n", frame);
+        }
+        output += &format!("length {}\n", self.length);
+        output
+    }
+
+    fn keys_at(&self, frame: u64) -> u16 {
+        let changes_so_far = self.changes.partition_point(|(start, _)| *start <= frame);
+        match changes_so_far {
+            0 => 0,
+            count => self.changes[count - 1].1,
+        }
+    }
+}
+
+fn to_state(mask: u16) -> [bool; 16] {
+    std::array::from_fn(|key| mask & (1 << key) != 0)
+}
+
+fn to_mask(state: &[bool; 16]) -> u16 {
+    state
+        .iter()
+        .enumerate()
+        .filter(|(_, held)| **held)
+        .fold(0, |mask, (key, _)| mask | (1 << key))
+}
+
+// plays a movie back instead of the real keypad. hotkeys from the real input
+// still work apart from reset, which would throw the replay off. the emulator
+// quits once the movie is over
+pub struct MoviePlayer {
+    inner: Box<dyn InputSource>,
+    movie: InputMovie,
+    frame: u64,
+    // how many of the movie's resets have been done
+    resets_done: usize,
+}
+
+impl MoviePlayer {
+    pub fn new(inner: Box<dyn InputSource>, movie: InputMovie) -> MoviePlayer {
+        MoviePlayer {
+            inner,
+            movie,
+            frame: 0,
+            resets_done: 0,
+        }
+    }
+}
+
+impl InputSource for MoviePlayer {
+    fn poll(&mut self, keyboard: &mut Keyboard) -> Vec<Hotkey> {
+        let mut hotkeys = self.inner.poll(keyboard);
+        hotkeys.retain(|hotkey| *hotkey != Hotkey::Reset);
+        if self.frame >= self.movie.length {
+            hotkeys.push(Hotkey::Quit);
+        }
+        if self.movie.resets.get(self.resets_done) == Some(&self.frame) {
+            self.resets_done += 1;
+            hotkeys.push(Hotkey::Reset);
+        }
+        keyboard.set_state(to_state(self.movie.keys_at(self.frame)));
+        hotkeys
+    }
+
+    fn frame_done(&mut self, keyboard: &Keyboard) {
+        self.inner.frame_done(keyboard);
+        self.frame += 1;
+    }
+}
+
+// passes the real input through and writes down what it did, the movie is
+// saved when the emulator is done with it
+pub struct MovieRecorder {
+    inner: Box<dyn InputSource>,
+    path: PathBuf,
+    movie: InputMovie,
+}
+
+impl MovieRecorder {
+    pub fn new(inner: Box<dyn InputSource>, path: PathBuf, seed: Option<u64>) -> MovieRecorder {
+        MovieRecorder {
+            inner,
+            path,
+            movie: InputMovie {
+                seed,
+                ..InputMovie::default()
+            },
+        }
+    }
+}
+
+impl InputSource for MovieRecorder {
+    fn poll(&mut self, keyboard: &mut Keyboard) -> Vec<Hotkey> {
+        let hotkeys = self.inner.poll(keyboard);
+        let frame = self.movie.length;
+        if hotkeys.contains(&Hotkey::Reset) && self.movie.resets.last() != Some(&frame) {
+            self.movie.resets.push(frame);
+        }
+        hotkeys
+    }
+
+    fn frame_done(&mut self, keyboard: &Keyboard) {
+        self.inner.frame_done(keyboard);
+        let mask = to_mask(keyboard.get_state());
+        let frame = self.movie.length;
+        if self.movie.keys_at(frame) != mask {
+            self.movie.changes.push((frame, mask));
+        }
+        self.movie.length += 1;
+    }
+}
+
+impl Drop for MovieRecorder {
+    fn drop(&mut self) {
+        if let Err(e) = fs::write(&self.path, self.movie.to_text()) {
+            eprintln!(
+                "warning: could not save the input movie to {}: {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn input_movie_round_trip_test() {
+        let movie =
+            InputMovie::parse("seed 7\n0 0000\n120 0010\n135 0000\nreset 300\nlength 600\n")
+                .unwrap();
+
+        assert_eq!(Some(7), movie.seed);
+        assert_eq!(0x0010, movie.keys_at(120));
+        assert_eq!(0x0010, movie.keys_at(134));
+        assert_eq!(0, movie.keys_at(135));
+        assert_eq!(movie, InputMovie::parse(&movie.to_text()).unwrap());
+        assert_eq!(0x8001, to_mask(&to_state(0x8001)));
+        assert!(InputMovie::parse("5 0001\n3 0000\n").is_err());
+        assert!(InputMovie::parse("reset 5\nreset 5\n").is_err());
+    }
+}
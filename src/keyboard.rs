@@ -1,82 +1,825 @@
-use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
-use sdl2::EventPump;
+use crate::debugger::MEMORY_ROWS;
+use crate::debugger_window::{DebuggerWindow, SharedWindowId};
+use crate::frontend::{Hotkey, InputSource};
+use crate::macros::Macro;
+use crate::settings::Settings;
+use sdl2::controller::{Axis, Button, GameController};
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::{Keycode, Scancode};
+use sdl2::mouse::MouseButton;
+use sdl2::{EventPump, GameControllerSubsystem};
+use std::cell::Cell;
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+
+// anything on the host that can be held down and bound to a keypad key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HostInput {
+    // by position, not by what's printed on the key
+    Key(Scancode),
+    Button(Button),
+    // a stick or trigger pushed past the deadzone, true for the positive direction
+    Axis(Axis, bool),
+    // a key on the on-screen keypad, always presses that keypad key
+    Touch(usize),
+}
+
+impl HostInput {
+    // sdl's controller names, sticks get a direction: "dpup", "a", "leftx-", "righttrigger+"
+    pub fn from_gamepad_name(name: &str) -> Option<HostInput> {
+        let name = name.to_lowercase();
+        if let Some(axis) = name.strip_suffix('+') {
+            return Axis::from_string(axis).map(|axis| HostInput::Axis(axis, true));
+        }
+        if let Some(axis) = name.strip_suffix('-') {
+            return Axis::from_string(axis).map(|axis| HostInput::Axis(axis, false));
+        }
+        Button::from_string(&name).map(HostInput::Button)
+    }
+
+    // a scancode name, or a gamepad control after "pad:" like "pad:x"
+    pub fn from_name(name: &str) -> Option<HostInput> {
+        match name.strip_prefix("pad:") {
+            Some(control) => HostInput::from_gamepad_name(control),
+            None => Scancode::from_name(name).map(HostInput::Key),
+        }
+    }
+
+    // the name it goes by in the config file
+    pub fn name(&self) -> String {
+        match self {
+            HostInput::Key(scancode) => scancode.name().to_string(),
+            HostInput::Button(button) => button.string(),
+            HostInput::Axis(axis, true) => format!("{}+", axis.string()),
+            HostInput::Axis(axis, false) => format!("{}-", axis.string()),
+            HostInput::Touch(key) => format!("on-screen {:X}", key),
+        }
+    }
+}
+
+// how the keys sit on the original hex keypad, row by row
+pub const KEYPAD_LAYOUT: [usize; 16] = [
+    0x1, 0x2, 0x3, 0xC, 0x4, 0x5, 0x6, 0xD, 0x7, 0x8, 0x9, 0xE, 0xA, 0x0, 0xB, 0xF,
+];
+
+// where the renderer last drew the on-screen keypad, in window coordinates, so
+// clicks and touches can be matched to keys. None while it's hidden
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeypadArea {
+    pub left: i32,
+    pub top: i32,
+    // the keypad is square, this is one side
+    pub size: u32,
+    pub window_size: (u32, u32),
+}
+
+pub type SharedKeypadArea = Rc<Cell<Option<KeypadArea>>>;
+
+impl KeypadArea {
+    pub fn key_at(&self, x: i32, y: i32) -> Option<usize> {
+        let (column, row) = (x - self.left, y - self.top);
+        let size = self.size as i32;
+        if !(0..size).contains(&column) || !(0..size).contains(&row) {
+            return None;
+        }
+        Some(KEYPAD_LAYOUT[(row * 4 / size * 4 + column * 4 / size) as usize])
+    }
+
+    // touch positions come in as fractions of the window
+    pub fn key_at_touch(&self, x: f32, y: f32) -> Option<usize> {
+        self.key_at(
+            (x * self.window_size.0 as f32) as i32,
+            (y * self.window_size.1 as f32) as i32,
+        )
+    }
+}
+
+// a keypad key changing between one frame and the next
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyEvent {
+    Pressed(u8),
+    Released(u8),
+}
 
 pub struct Keyboard {
     state: [bool; 16],
-    halt_key: usize,
+    // the state the emulator was last handed events for, events are the difference
+    previous: [bool; 16],
+    bindings: HashMap<HostInput, usize>,
+    // inputs that flick a keypad key on and off while they're held
+    turbo: HashMap<HostInput, usize>,
+    // frames per on/off cycle
+    turbo_period: u64,
+    macros: HashMap<HostInput, Macro>,
+    // macros that are running and the frame they started on
+    playing: Vec<(HostInput, u64)>,
+    // bound inputs that are down right now and the frame they went down on.
+    // a keypad key stays pressed until everything bound to it is let go
+    held: HashMap<HostInput, u64>,
+    // counts emulated frames, for turbo and macro timing
+    frame: u64,
+    // how far a stick has to move before it counts as pressed
+    deadzone: i32,
+    // while rebinding, the next input is caught here instead of pressing anything
+    capturing: bool,
+    captured: Option<HostInput>,
 }
 
 impl Keyboard {
     pub fn new() -> Keyboard {
         Keyboard {
             state: [false; 16],
-            halt_key: 256, //256 bc i want to embarrass bytes and their storage capacity
+            previous: [false; 16],
+            bindings: HashMap::new(),
+            turbo: HashMap::new(),
+            turbo_period: 6,
+            macros: HashMap::new(),
+            playing: Vec::new(),
+            held: HashMap::new(),
+            frame: 0,
+            deadzone: 0,
+            capturing: false,
+            captured: None,
         }
     }
 
-    pub fn parse_keyboard_event(&mut self, event_pump: &mut EventPump) -> bool {
-        for event in event_pump.poll_iter() {
-            match event {
-                Event::Quit { .. }
-                | Event::KeyDown {
-                    keycode: Some(Keycode::Escape),
-                    ..
-                } => return false,
-                Event::KeyDown {
-                    keycode: Some(keycode),
-                    ..
-                } => {
-                    if let Some(key) = Keyboard::get_key_pressed(keycode) {
-                        self.state[key] = true;
-                    }
-                }
-                Event::KeyUp {
-                    keycode: Some(keycode),
-                    ..
-                } => {
-                    if let Some(key) = Keyboard::get_key_pressed(keycode) {
-                        self.state[key] = false;
-                    }
-                }
-                _ => {}
+    pub fn build(settings: &Settings) -> Result<Keyboard, String> {
+        let mut keyboard = Keyboard::with_bindings(&settings.keys)?;
+        for (control, keypad_key) in &settings.gamepad {
+            let Some(keypad_key) = parse_keypad_key(keypad_key)? else {
+                continue;
+            };
+            let input = HostInput::from_gamepad_name(control)
+                .ok_or_else(|| format!("unknown gamepad control '{}'", control))?;
+            keyboard.bindings.insert(input, keypad_key);
+        }
+        keyboard.deadzone = i16::MAX as i32 * settings.gamepad_deadzone as i32 / 100;
+
+        let named = |name: &str| {
+            HostInput::from_name(name).ok_or_else(|| format!("unknown key or control '{}'", name))
+        };
+        for (host_input, keypad_key) in &settings.turbo {
+            if let Some(keypad_key) = parse_keypad_key(keypad_key)? {
+                keyboard.turbo.insert(named(host_input)?, keypad_key);
             }
         }
+        keyboard.turbo_period = (60 / settings.turbo_rate.max(1) as u64).max(2);
+        for (host_input, sequence) in &settings.macros {
+            keyboard
+                .macros
+                .insert(named(host_input)?, Macro::parse(sequence)?);
+        }
+        Ok(keyboard)
+    }
 
-        true
+    // keys maps keypad keys ("0" to "F") to sdl scancode names, like the
+    // config file. a host key bound twice goes to the last keypad key
+    pub fn with_bindings(keys: &BTreeMap<String, Vec<String>>) -> Result<Keyboard, String> {
+        let mut keyboard = Keyboard::new();
+        for (keypad_key, host_keys) in keys {
+            let Some(keypad_key) = parse_keypad_key(keypad_key)? else {
+                continue;
+            };
+            for host_key in host_keys {
+                let scancode = Scancode::from_name(host_key)
+                    .ok_or_else(|| format!("unknown key name '{}'", host_key))?;
+                keyboard
+                    .bindings
+                    .insert(HostInput::Key(scancode), keypad_key);
+            }
+        }
+
+        Ok(keyboard)
+    }
+
+    // swaps in new bindings, e.g. after rebinding
+    pub fn set_bindings(&mut self, settings: &Settings) -> Result<(), String> {
+        let rebuilt = Keyboard::build(settings)?;
+        self.bindings = rebuilt.bindings;
+        self.turbo = rebuilt.turbo;
+        self.macros = rebuilt.macros;
+        self.playing.clear();
+        self.held.clear();
+        self.update_state();
+        Ok(())
+    }
+
+    pub fn start_capture(&mut self) {
+        self.capturing = true;
+        self.captured = None;
+        self.held.clear();
+        self.playing.clear();
+        self.update_state();
+    }
+
+    pub fn stop_capture(&mut self) {
+        self.capturing = false;
+        self.captured = None;
+    }
+
+    pub fn take_captured(&mut self) -> Option<HostInput> {
+        self.captured.take()
+    }
+
+    pub fn handle_event(&mut self, event: Event) -> Option<Hotkey> {
+        match event {
+            Event::KeyDown {
+                keycode: Some(Keycode::Escape | Keycode::F8),
+                repeat: false,
+                ..
+            } if self.capturing => return Some(Hotkey::Rebind),
+            Event::KeyDown {
+                keycode: Some(Keycode::F8),
+                repeat: false,
+                ..
+            } => return Some(Hotkey::Rebind),
+            Event::Quit { .. }
+            | Event::KeyDown {
+                keycode: Some(Keycode::Escape),
+                ..
+            } => return Some(Hotkey::Quit),
+            Event::KeyDown {
+                keycode: Some(Keycode::F1),
+                repeat: false,
+                ..
+            } => return Some(Hotkey::ToggleHud),
+            Event::KeyDown {
+                keycode: Some(Keycode::F2),
+                repeat: false,
+                ..
+            } => return Some(Hotkey::NextTheme),
+            Event::KeyDown {
+                keycode: Some(Keycode::F3),
+                repeat: false,
+                ..
+            } => return Some(Hotkey::NextFilter),
+            Event::KeyDown {
+                keycode: Some(Keycode::F11),
+                repeat: false,
+                ..
+            } => return Some(Hotkey::ToggleFullscreen),
+            Event::KeyDown {
+                keycode: Some(Keycode::F12),
+                repeat: false,
+                ..
+            } => return Some(Hotkey::Screenshot),
+            Event::KeyDown {
+                keycode: Some(Keycode::F4),
+                repeat: false,
+                ..
+            } => return Some(Hotkey::ToggleDebugger),
+            Event::KeyDown {
+                keycode: Some(Keycode::F5),
+                repeat: false,
+                ..
+            } => return Some(Hotkey::Pause),
+            // held down, it keeps stepping
+            Event::KeyDown {
+                keycode: Some(Keycode::F6),
+                ..
+            } => return Some(Hotkey::Step),
+            Event::KeyDown {
+                keycode: Some(Keycode::F10),
+                ..
+            } => return Some(Hotkey::FrameAdvance),
+            Event::KeyDown {
+                keycode: Some(Keycode::F7),
+                repeat: false,
+                ..
+            } => return Some(Hotkey::ToggleKeypad),
+            Event::KeyDown {
+                keycode: Some(Keycode::F9),
+                repeat: false,
+                ..
+            } => return Some(Hotkey::ToggleRecording),
+            Event::KeyDown {
+                scancode: Some(scancode),
+                repeat: false,
+                ..
+            } if self.capturing => self.captured = Some(HostInput::Key(scancode)),
+            // these could be game keys, so they're left alone while rebinding and
+            // once a keypad key is bound to them
+            Event::KeyDown {
+                keycode: Some(Keycode::Backspace),
+                scancode: Some(scancode),
+                repeat: false,
+                ..
+            } if !self.is_bound(&HostInput::Key(scancode)) => return Some(Hotkey::Reset),
+            Event::KeyDown {
+                keycode: Some(Keycode::Tab),
+                scancode: Some(scancode),
+                repeat: false,
+                ..
+            } if !self.is_bound(&HostInput::Key(scancode)) => {
+                return Some(Hotkey::ToggleFastForward)
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::Backquote),
+                scancode: Some(scancode),
+                repeat: false,
+                ..
+            } if !self.is_bound(&HostInput::Key(scancode)) => {
+                return Some(Hotkey::ToggleSlowMotion)
+            }
+            Event::ControllerButtonDown { button, .. } if self.capturing => {
+                self.captured = Some(HostInput::Button(button))
+            }
+            // a stick has to go most of the way, so drift doesn't get picked up
+            Event::ControllerAxisMotion { axis, value, .. }
+                if self.capturing
+                    && (value as i32).abs() > self.deadzone.max(i16::MAX as i32 / 2) =>
+            {
+                self.captured = Some(HostInput::Axis(axis, value > 0))
+            }
+            _ if self.capturing => {}
+            Event::KeyDown {
+                scancode: Some(scancode),
+                ..
+            } => self.press(HostInput::Key(scancode)),
+            Event::KeyUp {
+                scancode: Some(scancode),
+                ..
+            } => self.release(HostInput::Key(scancode)),
+            Event::ControllerButtonDown { button, .. } => self.press(HostInput::Button(button)),
+            Event::ControllerButtonUp { button, .. } => self.release(HostInput::Button(button)),
+            Event::ControllerAxisMotion { axis, value, .. } => self.move_axis(axis, value),
+            _ => {}
+        }
+        None
+    }
+
+    fn is_bound(&self, input: &HostInput) -> bool {
+        self.bindings.contains_key(input)
+            || self.turbo.contains_key(input)
+            || self.macros.contains_key(input)
+    }
+
+    pub fn press(&mut self, input: HostInput) {
+        let bound = self.bindings.contains_key(&input)
+            || self.turbo.contains_key(&input)
+            || matches!(input, HostInput::Touch(_));
+        if self.macros.contains_key(&input) && !self.held.contains_key(&input) {
+            // a macro plays to the end once started, pressing it again restarts it
+            self.playing.retain(|(playing, _)| *playing != input);
+            self.playing.push((input, self.frame));
+        } else if !bound {
+            return;
+        }
+        self.held.entry(input).or_insert(self.frame);
+        self.update_state();
+    }
+
+    pub fn release(&mut self, input: HostInput) {
+        if self.held.remove(&input).is_some() {
+            self.update_state();
+        }
+    }
+
+    // called after every emulated frame, so turbo and macros stand still while
+    // the game is paused
+    pub fn next_frame(&mut self) {
+        self.frame += 1;
+        let (frame, macros) = (self.frame, &self.macros);
+        self.playing
+            .retain(|(input, start)| macros[input].keys_at(frame - start).is_some());
+        self.update_state();
+    }
+
+    // a stick is two inputs, one for each direction
+    pub fn move_axis(&mut self, axis: Axis, value: i16) {
+        let value = value as i32;
+        for (positive, pushed) in [
+            (true, value > self.deadzone),
+            (false, value < -self.deadzone),
+        ] {
+            match pushed {
+                true => self.press(HostInput::Axis(axis, positive)),
+                false => self.release(HostInput::Axis(axis, positive)),
+            }
+        }
+    }
+
+    // when a controller goes away it can't send the releases any more
+    pub fn release_gamepad(&mut self) {
+        self.held
+            .retain(|input, _| matches!(input, HostInput::Key(_) | HostInput::Touch(_)));
+        self.update_state();
+    }
+
+    fn update_state(&mut self) {
+        self.state = [false; 16];
+        for (input, since) in &self.held {
+            if let Some(key) = self.bindings.get(input) {
+                self.state[*key] = true;
+            }
+            if let HostInput::Touch(key) = input {
+                self.state[*key] = true;
+            }
+            // turbo starts out pressed, then spends half of each cycle up
+            if let Some(key) = self.turbo.get(input) {
+                if (self.frame - since) % self.turbo_period < self.turbo_period.div_ceil(2) {
+                    self.state[*key] = true;
+                }
+            }
+        }
+        for (input, start) in &self.playing {
+            let mask = self.macros[input].keys_at(self.frame - start).unwrap_or(0);
+            for key in 0..16 {
+                self.state[key] |= mask & (1 << key) != 0;
+            }
+        }
     }
 
     pub fn get_state(&self) -> &[bool; 16] {
         &self.state
     }
 
-    pub fn set_halt_key(&mut self, key: u8) {
-        self.halt_key = key as usize;
-    }
-
-    pub fn get_halt_key(&self) -> usize {
-        self.halt_key as usize
-    }
-
-    fn get_key_pressed(key_pressed: Keycode) -> Option<usize> {
-        match key_pressed {
-            Keycode::Num0 => Some(0),
-            Keycode::Num1 => Some(1),
-            Keycode::Num2 => Some(2),
-            Keycode::Num3 => Some(3),
-            Keycode::Num4 => Some(4),
-            Keycode::Num5 => Some(5),
-            Keycode::Num6 => Some(6),
-            Keycode::Num7 => Some(7),
-            Keycode::Num8 => Some(8),
-            Keycode::Num9 => Some(9),
-            Keycode::A => Some(10),
-            Keycode::B => Some(11),
-            Keycode::C => Some(12),
-            Keycode::D => Some(13),
-            Keycode::E => Some(14),
-            Keycode::F => Some(15),
+    // for input that doesn't come from key events, like replays
+    pub fn set_state(&mut self, state: [bool; 16]) {
+        self.state = state;
+    }
+
+    // what changed since the emulator last asked, lowest key first
+    pub fn take_events(&mut self) -> Vec<KeyEvent> {
+        let events = (0..16u8)
+            .filter_map(
+                |key| match (self.previous[key as usize], self.state[key as usize]) {
+                    (false, true) => Some(KeyEvent::Pressed(key)),
+                    (true, false) => Some(KeyEvent::Released(key)),
+                    _ => None,
+                },
+            )
+            .collect();
+        self.previous = self.state;
+        events
+    }
+}
+
+// "none" unbinds, so a rom profile can take a control away from the global one
+pub fn parse_keypad_key(keypad_key: &str) -> Result<Option<usize>, String> {
+    if keypad_key.eq_ignore_ascii_case("none") {
+        return Ok(None);
+    }
+    usize::from_str_radix(keypad_key, 16)
+        .ok()
+        .filter(|key| *key < 16)
+        .map(Some)
+        .ok_or_else(|| format!("'{}' is not a keypad key (0-F)", keypad_key))
+}
+
+// keyboard and gamepad input straight from the sdl event queue
+pub struct SdlInput {
+    event_pump: EventPump,
+    // None when sdl couldn't start its controller support, the keyboard still works
+    game_controller: Option<GameControllerSubsystem>,
+    // open controllers by instance id, they're closed when dropped
+    controllers: HashMap<u32, GameController>,
+    keypad_area: SharedKeypadArea,
+    pointers: Pointers,
+    debugger_window_id: SharedWindowId,
+}
+
+// sdl turns touches into mouse clicks as well, those have this mouse id
+const TOUCH_MOUSE_ID: u32 = u32::MAX;
+
+// on-screen keys being held down, by finger id or None for the mouse
+#[derive(Default)]
+struct Pointers {
+    held: HashMap<Option<i64>, usize>,
+}
+
+impl Pointers {
+    // false for anything that isn't a click or a touch on the game window
+    fn handle_event(
+        &mut self,
+        event: &Event,
+        keypad_area: Option<KeypadArea>,
+        debugger_window_id: Option<u32>,
+        keyboard: &mut Keyboard,
+    ) -> bool {
+        match *event {
+            Event::MouseButtonDown {
+                window_id,
+                which,
+                mouse_btn: MouseButton::Left,
+                x,
+                y,
+                ..
+            } if which != TOUCH_MOUSE_ID && Some(window_id) != debugger_window_id => {
+                let key = keypad_area.and_then(|area| area.key_at(x, y));
+                self.point(None, key, keyboard);
+            }
+            Event::MouseButtonUp {
+                window_id,
+                which,
+                mouse_btn: MouseButton::Left,
+                ..
+            } if which != TOUCH_MOUSE_ID && Some(window_id) != debugger_window_id => {
+                self.lift(None, keyboard)
+            }
+            Event::FingerDown {
+                finger_id, x, y, ..
+            } => {
+                let key = keypad_area.and_then(|area| area.key_at_touch(x, y));
+                self.point(Some(finger_id), key, keyboard);
+            }
+            Event::FingerUp { finger_id, .. } => self.lift(Some(finger_id), keyboard),
+            _ => return false,
+        }
+        true
+    }
+
+    fn point(&mut self, pointer: Option<i64>, key: Option<usize>, keyboard: &mut Keyboard) {
+        if let Some(key) = key {
+            self.held.insert(pointer, key);
+            keyboard.press(HostInput::Touch(key));
+        }
+    }
+
+    fn lift(&mut self, pointer: Option<i64>, keyboard: &mut Keyboard) {
+        let Some(key) = self.held.remove(&pointer) else {
+            return;
+        };
+        // another finger might still be on the same key
+        if !self.held.values().any(|held| *held == key) {
+            keyboard.release(HostInput::Touch(key));
+        }
+    }
+}
+
+impl SdlInput {
+    pub fn new(
+        event_pump: EventPump,
+        game_controller: Option<GameControllerSubsystem>,
+        keypad_area: SharedKeypadArea,
+        debugger_window_id: SharedWindowId,
+    ) -> SdlInput {
+        SdlInput {
+            event_pump,
+            game_controller,
+            controllers: HashMap::new(),
+            keypad_area,
+            pointers: Pointers::default(),
+            debugger_window_id,
+        }
+    }
+
+    // the debugger window's own keys and clicks. anything else typed into it
+    // still works like it would in the game window
+    fn debugger_hotkey(event: &Event) -> Option<Hotkey> {
+        match event {
+            Event::Window {
+                win_event: WindowEvent::Close,
+                ..
+            }
+            | Event::KeyDown {
+                keycode: Some(Keycode::Escape),
+                ..
+            } => Some(Hotkey::ToggleDebugger),
+            Event::KeyDown {
+                keycode: Some(Keycode::Space),
+                repeat: false,
+                ..
+            } => Some(Hotkey::Pause),
+            Event::KeyDown {
+                keycode: Some(Keycode::S),
+                ..
+            } => Some(Hotkey::Step),
+            Event::KeyDown {
+                keycode: Some(Keycode::Up),
+                ..
+            } => Some(Hotkey::DebuggerScroll(-1)),
+            Event::KeyDown {
+                keycode: Some(Keycode::Down),
+                ..
+            } => Some(Hotkey::DebuggerScroll(1)),
+            Event::KeyDown {
+                keycode: Some(Keycode::PageUp),
+                ..
+            } => Some(Hotkey::DebuggerScroll(-(MEMORY_ROWS as i32))),
+            Event::KeyDown {
+                keycode: Some(Keycode::PageDown),
+                ..
+            } => Some(Hotkey::DebuggerScroll(MEMORY_ROWS as i32)),
+            Event::MouseWheel { y, .. } => Some(Hotkey::DebuggerScroll(-y)),
+            Event::MouseButtonDown {
+                mouse_btn: MouseButton::Left,
+                x,
+                y,
+                ..
+            } => DebuggerWindow::cell_at(*x, *y)
+                .map(|(column, row)| Hotkey::DebuggerClick { column, row }),
             _ => None,
         }
     }
+
+    // sdl also sends an added event for every controller that was already
+    // plugged in at startup, so this covers those too
+    fn connect(&mut self, joystick_index: u32) {
+        let Some(game_controller) = &self.game_controller else {
+            return;
+        };
+        match game_controller.open(joystick_index) {
+            Ok(controller) => {
+                eprintln!("gamepad connected: {}", controller.name());
+                self.controllers
+                    .insert(controller.instance_id(), controller);
+            }
+            Err(e) => eprintln!("warning: could not open gamepad: {}", e),
+        }
+    }
+
+    fn disconnect(&mut self, instance_id: u32, keyboard: &mut Keyboard) {
+        if let Some(controller) = self.controllers.remove(&instance_id) {
+            eprintln!("gamepad disconnected: {}", controller.name());
+            keyboard.release_gamepad();
+        }
+    }
+}
+
+impl InputSource for SdlInput {
+    fn poll(&mut self, keyboard: &mut Keyboard) -> Vec<Hotkey> {
+        let mut hotkeys = Vec::new();
+        let keypad_area = self.keypad_area.get();
+        let debugger_window_id = self.debugger_window_id.get();
+        let events: Vec<Event> = self.event_pump.poll_iter().collect();
+        for event in events {
+            if debugger_window_id.is_some() && event.get_window_id() == debugger_window_id {
+                if let Some(hotkey) = SdlInput::debugger_hotkey(&event) {
+                    hotkeys.push(hotkey);
+                    continue;
+                }
+            }
+            if self
+                .pointers
+                .handle_event(&event, keypad_area, debugger_window_id, keyboard)
+            {
+                continue;
+            }
+            match event {
+                // sdl only sends a quit event once the last window is closed,
+                // the game window going should quit even with the debugger open
+                Event::Window {
+                    win_event: WindowEvent::Close,
+                    ..
+                } => hotkeys.push(Hotkey::Quit),
+                Event::ControllerDeviceAdded { which, .. } => self.connect(which),
+                Event::ControllerDeviceRemoved { which, .. } => self.disconnect(which, keyboard),
+                event => hotkeys.extend(keyboard.handle_event(event)),
+            }
+        }
+        hotkeys
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn keys_and_gamepad_share_keypad_keys_test() {
+        let mut keyboard = Keyboard::new();
+        keyboard.deadzone = 8000;
+        keyboard.bindings.insert(HostInput::Key(Scancode::A), 4);
+        keyboard
+            .bindings
+            .insert(HostInput::Button(Button::DPadLeft), 4);
+        keyboard
+            .bindings
+            .insert(HostInput::Axis(Axis::LeftX, false), 4);
+        keyboard
+            .bindings
+            .insert(HostInput::Axis(Axis::LeftX, true), 6);
+
+        keyboard.press(HostInput::Key(Scancode::A));
+        keyboard.press(HostInput::Button(Button::DPadLeft));
+        keyboard.release(HostInput::Key(Scancode::A));
+        assert!(keyboard.get_state()[4]);
+        keyboard.release(HostInput::Button(Button::DPadLeft));
+        assert!(!keyboard.get_state()[4]);
+
+        // inside the deadzone is nothing, past it one direction at a time
+        keyboard.move_axis(Axis::LeftX, 4000);
+        assert!(!keyboard.get_state()[6]);
+        keyboard.move_axis(Axis::LeftX, 20000);
+        assert!(keyboard.get_state()[6]);
+        keyboard.move_axis(Axis::LeftX, -20000);
+        assert!(keyboard.get_state()[4] && !keyboard.get_state()[6]);
+
+        keyboard.release_gamepad();
+        assert_eq!(&[false; 16], keyboard.get_state());
+    }
+
+    #[test]
+    fn keypad_area_hit_test_test() {
+        let area = KeypadArea {
+            left: 100,
+            top: 0,
+            size: 80,
+            window_size: (200, 100),
+        };
+        assert_eq!(Some(0x1), area.key_at(100, 0));
+        assert_eq!(Some(0xF), area.key_at(179, 79));
+        assert_eq!(Some(0x0), area.key_at_touch(0.65, 0.7));
+        assert_eq!(None, area.key_at(99, 10));
+    }
+
+    #[test]
+    fn touch_and_debugger_releases_leave_the_mouse_alone_test() {
+        let area = KeypadArea {
+            left: 100,
+            top: 0,
+            size: 80,
+            window_size: (200, 100),
+        };
+        let mouse = |down: bool, window_id: u32, which: u32| match down {
+            true => Event::MouseButtonDown {
+                timestamp: 0,
+                window_id,
+                which,
+                mouse_btn: MouseButton::Left,
+                clicks: 1,
+                x: 100,
+                y: 0,
+            },
+            false => Event::MouseButtonUp {
+                timestamp: 0,
+                window_id,
+                which,
+                mouse_btn: MouseButton::Left,
+                clicks: 1,
+                x: 100,
+                y: 0,
+            },
+        };
+        let mut keyboard = Keyboard::new();
+        let mut pointers = Pointers::default();
+        let mut handle = |event: Event, keyboard: &mut Keyboard| {
+            pointers.handle_event(&event, Some(area), Some(2), keyboard)
+        };
+
+        assert!(handle(mouse(true, 1, 0), &mut keyboard));
+        assert!(keyboard.get_state()[0x1]);
+        // a finger lifting somewhere and a release in the debugger window
+        handle(mouse(false, 1, TOUCH_MOUSE_ID), &mut keyboard);
+        handle(mouse(false, 2, 0), &mut keyboard);
+        assert!(keyboard.get_state()[0x1]);
+        handle(mouse(false, 1, 0), &mut keyboard);
+        assert!(!keyboard.get_state()[0x1]);
+    }
+
+    #[test]
+    fn bound_keys_win_over_hotkeys_test() {
+        let key_down = |keycode: Keycode, scancode: Scancode| Event::KeyDown {
+            timestamp: 0,
+            window_id: 0,
+            keycode: Some(keycode),
+            scancode: Some(scancode),
+            keymod: sdl2::keyboard::Mod::NOMOD,
+            repeat: false,
+        };
+        let mut keyboard = Keyboard::new();
+        keyboard.bindings.insert(HostInput::Key(Scancode::Tab), 0xA);
+
+        assert_eq!(
+            None,
+            keyboard.handle_event(key_down(Keycode::Tab, Scancode::Tab))
+        );
+        assert!(keyboard.get_state()[0xA]);
+        assert_eq!(
+            Some(Hotkey::Reset),
+            keyboard.handle_event(key_down(Keycode::Backspace, Scancode::Backspace))
+        );
+    }
+
+    #[test]
+    fn turbo_and_macros_test() {
+        let mut keyboard = Keyboard::new();
+        keyboard.turbo.insert(HostInput::Key(Scancode::Space), 5);
+        keyboard.turbo_period = 4;
+        keyboard.macros.insert(
+            HostInput::Key(Scancode::M),
+            Macro::parse("8:1 -:1 8:1").unwrap(),
+        );
+
+        keyboard.press(HostInput::Key(Scancode::Space));
+        let mut turbo = Vec::new();
+        for _ in 0..6 {
+            turbo.push(keyboard.get_state()[5]);
+            keyboard.next_frame();
+        }
+        assert_eq!(vec![true, true, false, false, true, true], turbo);
+        keyboard.release(HostInput::Key(Scancode::Space));
+
+        // a tap plays the whole macro
+        keyboard.press(HostInput::Key(Scancode::M));
+        keyboard.release(HostInput::Key(Scancode::M));
+        let mut played = Vec::new();
+        for _ in 0..4 {
+            played.push(keyboard.get_state()[8]);
+            keyboard.next_frame();
+        }
+        assert_eq!(vec![true, false, true, false], played);
+    }
 }
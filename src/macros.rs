@@ -0,0 +1,84 @@
+// a timed sequence of keypad presses played from a single host key. written
+// in the config as steps separated by spaces, each one the keypad keys to hold
+// and for how many frames:
+//
+//   "5:10 -:2 8"
+//
+// holds 5 for 10 frames, lets go of everything for 2, then holds 8. "58:3"
+// holds 5 and 8 together, and a step without a frame count lasts 4 frames
+const DEFAULT_STEP_FRAMES: u64 = 4;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Macro {
+    // held keys as a mask (bit n for key n) and how many frames to hold them
+    steps: Vec<(u16, u64)>,
+}
+
+impl Macro {
+    pub fn parse(sequence: &str) -> Result<Macro, String> {
+        let mut steps = Vec::new();
+        for step in sequence.split_whitespace() {
+            let invalid = || format!("can't read step '{}' of '{}'", step, sequence);
+            let (keys, frames) = match step.split_once(':') {
+                Some((keys, frames)) => (keys, frames.parse().map_err(|_| invalid())?),
+                None => (step, DEFAULT_STEP_FRAMES),
+            };
+            if frames == 0 {
+                return Err(invalid());
+            }
+
+            let mut mask = 0;
+            if keys != "-" {
+                for key in keys.chars() {
+                    mask |= 1 << key.to_digit(16).ok_or_else(invalid)?;
+                }
+            }
+            steps.push((mask, frames));
+        }
+
+        if steps.is_empty() {
+            return Err(String::from("a macro needs at least one step"));
+        }
+        Ok(Macro { steps })
+    }
+
+    // the keys held on this frame of the macro, None once it's over
+    pub fn keys_at(&self, mut frame: u64) -> Option<u16> {
+        for (mask, frames) in &self.steps {
+            if frame < *frames {
+                return Some(*mask);
+            }
+            frame -= frames;
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn macro_steps_test() {
+        let steps = Macro::parse("5:2 -:1 a8").unwrap();
+
+        let played: Vec<Option<u16>> = (0..8).map(|frame| steps.keys_at(frame)).collect();
+        assert_eq!(
+            vec![
+                Some(0x0020),
+                Some(0x0020),
+                Some(0),
+                Some(0x0500),
+                Some(0x0500),
+                Some(0x0500),
+                Some(0x0500),
+                None
+            ],
+            played
+        );
+        assert!(Macro::parse("5:0").is_err());
+        assert!(Macro::parse("G").is_err());
+        assert!(Macro::parse("").is_err());
+    }
+}
@@ -1,16 +1,72 @@
 extern crate sdl2;
+mod assembler;
 mod audio;
+mod beeper;
 mod chip8;
+mod cli;
+mod config;
+mod database;
+mod debugger;
+mod debugger_window;
+mod disassembler;
 mod display;
 mod emulator;
+mod filters;
+mod font;
+mod frontend;
+mod hud;
+mod input_movie;
 mod keyboard;
+mod macros;
+mod octo;
+mod octocart;
+mod palette;
+mod phosphor;
 mod pixel;
+mod quirks;
+mod rebind;
 mod renderer;
+mod rom;
+mod screenshot;
+mod settings;
+mod terminal;
+mod video_recorder;
+mod wav_recorder;
+use crate::cli::{Cli, Command};
 use crate::emulator::Emulator;
+use clap::Parser;
+use std::fs;
 
 pub fn main() -> Result<(), String> {
-    let mut emulator = Emulator::build()?;
-    emulator.run(String::from("roms/particle_demo.ch8"))?;
+    let cli = Cli::parse();
+
+    match cli.command {
+        Command::Run(args) => {
+            if args.print_config {
+                let settings = config::resolve_settings(&args)?;
+                print!("{}", config::print_config(&args, &settings)?);
+                return Ok(());
+            }
+
+            let mut emulator = Emulator::build(&args)?;
+            emulator.run(args.rom)?;
+        }
+        Command::Asm { input, output } => {
+            let source = fs::read_to_string(&input)
+                .map_err(|e| format!("Error opening {}: {}", input.display(), e))?;
+            let rom =
+                assembler::assemble(&source).map_err(|e| format!("{}: {}", input.display(), e))?;
+            fs::write(&output, rom)
+                .map_err(|e| format!("Error writing {}: {}", output.display(), e))?;
+        }
+        Command::Disasm { rom } => {
+            let rom_contents =
+                fs::read(&rom).map_err(|e| format!("Error opening {}: {}", rom.display(), e))?;
+            disassembler::disassemble(&rom_contents, 0x200)
+                .iter()
+                .for_each(|line| println!("{}", line));
+        }
+    }
 
     Ok(())
 }
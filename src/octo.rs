@@ -0,0 +1,971 @@
+use std::collections::{HashMap, VecDeque};
+
+// compiles octo, the language octocarts carry their programs in:
+//
+//   :alias x v0
+//   : main
+//     x := 10
+//     loop
+//       sprite x x 5
+//       if x == 60 then x := 0
+//       x += 1
+//     again
+//
+// covers labels, :const, :alias, :unpack, :next, :org, :macro, :calc, :byte,
+// :pointer, if/then, if/begin/else/end, loop/while/again and every chip-8,
+// schip and xo-chip instruction. names can be used before their label
+const START: u32 = 0x200;
+// a macro that expands into itself would otherwise never finish
+const MAX_EXPANSIONS: usize = 100_000;
+
+#[derive(Debug, Clone)]
+struct Token {
+    text: String,
+    line: usize,
+}
+
+// how a name's value gets written into the program once it's known
+#[derive(Debug, Clone, Copy)]
+enum Patch {
+    // the low 12 bits of an instruction
+    Address,
+    // two whole bytes, for :pointer and i := long
+    Long,
+    // the immediates of the v0 := and v1 := pair from :unpack, with the high
+    // nibble to put above the address, None for :unpack long
+    Unpack(Option<u8>),
+}
+
+// a name used before its label, filled in at the end
+struct Fixup {
+    address: u32,
+    name: String,
+    patch: Patch,
+    line: usize,
+}
+
+// the open if ... begin and loop blocks, innermost last
+enum Block {
+    // the jump over the body, or over the else part once there is one
+    If { jump: u32, has_else: bool },
+    // the jumps out of the loop from its whiles
+    Loop { start: u32, exits: Vec<u32> },
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Operand {
+    Register(u8),
+    Value(u8),
+}
+
+struct Condition {
+    register: u8,
+    comparison: String,
+    // key and -key don't take one
+    operand: Option<Operand>,
+}
+
+#[derive(Clone)]
+struct Macro {
+    arguments: Vec<String>,
+    body: Vec<Token>,
+}
+
+struct Compiler {
+    tokens: VecDeque<Token>,
+    // where the token being compiled came from, for errors
+    line: usize,
+    rom: Vec<u8>,
+    here: u32,
+    labels: HashMap<String, u32>,
+    constants: HashMap<String, f64>,
+    aliases: HashMap<String, u8>,
+    macros: HashMap<String, Macro>,
+    fixups: Vec<Fixup>,
+    blocks: Vec<Block>,
+    expansions: usize,
+}
+
+pub fn compile(source: &str) -> Result<Vec<u8>, String> {
+    Compiler::new(source).compile()
+}
+
+fn tokenize(source: &str) -> VecDeque<Token> {
+    let mut tokens = VecDeque::new();
+    for (index, line) in source.lines().enumerate() {
+        let mut rest = line.trim_start();
+        while !rest.is_empty() && !rest.starts_with('#') {
+            // strings can have spaces in them
+            let end = match rest.strip_prefix('"') {
+                Some(quoted) => quoted.find('"').map_or(rest.len(), |end| end + 2),
+                None => rest.find(char::is_whitespace).unwrap_or(rest.len()),
+            };
+            tokens.push_back(Token {
+                text: rest[..end].to_string(),
+                line: index + 1,
+            });
+            rest = rest[end..].trim_start();
+        }
+    }
+    tokens
+}
+
+// decimal, 0x hex or 0b binary, any of them negative
+fn parse_number(text: &str) -> Option<f64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let value = if let Some(hex) = digits.strip_prefix("0x") {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if let Some(binary) = digits.strip_prefix("0b") {
+        i64::from_str_radix(binary, 2).ok()?
+    } else if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
+        digits.parse().ok()?
+    } else {
+        return None;
+    };
+    Some(if negative { -value } else { value } as f64)
+}
+
+impl Compiler {
+    fn new(source: &str) -> Compiler {
+        // octo's names for the keypad keys, by where they sit on a qwerty keyboard
+        let keys = [
+            ("1", 0x1),
+            ("2", 0x2),
+            ("3", 0x3),
+            ("4", 0xC),
+            ("Q", 0x4),
+            ("W", 0x5),
+            ("E", 0x6),
+            ("R", 0xD),
+            ("A", 0x7),
+            ("S", 0x8),
+            ("D", 0x9),
+            ("F", 0xE),
+            ("Z", 0xA),
+            ("X", 0x0),
+            ("C", 0xB),
+            ("V", 0xF),
+        ];
+        Compiler {
+            tokens: tokenize(source),
+            line: 1,
+            rom: Vec::new(),
+            here: START,
+            labels: HashMap::new(),
+            constants: keys
+                .iter()
+                .map(|(name, key)| (format!("OCTO_KEY_{}", name), *key as f64))
+                .collect(),
+            aliases: HashMap::new(),
+            macros: HashMap::new(),
+            fixups: Vec::new(),
+            blocks: Vec::new(),
+            expansions: 0,
+        }
+    }
+
+    fn compile(mut self) -> Result<Vec<u8>, String> {
+        while let Some(token) = self.tokens.pop_front() {
+            self.line = token.line;
+            self.statement(token)?;
+        }
+
+        if !self.blocks.is_empty() {
+            return Err(self.error("an if ... begin or a loop is never closed"));
+        }
+        if !self.labels.contains_key("main") {
+            return Err(String::from(
+                "there's no main label, octo programs start there",
+            ));
+        }
+        for fixup in std::mem::take(&mut self.fixups) {
+            self.line = fixup.line;
+            let value = self
+                .lookup(&fixup.name)
+                .ok_or_else(|| self.error(&format!("'{}' is never defined", fixup.name)))?;
+            self.patch(fixup.address, value, fixup.patch)?;
+        }
+        Ok(self.rom)
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("line {}: {}", self.line, message)
+    }
+
+    fn next(&mut self) -> Result<Token, String> {
+        let token = self
+            .tokens
+            .pop_front()
+            .ok_or_else(|| self.error("the program ends in the middle of a statement"))?;
+        self.line = token.line;
+        Ok(token)
+    }
+
+    fn peek(&self) -> Option<&str> {
+        self.tokens.front().map(|token| token.text.as_str())
+    }
+
+    fn expect(&mut self, text: &str) -> Result<(), String> {
+        let token = self.next()?;
+        if token.text != text {
+            return Err(self.error(&format!("expected '{}', got '{}'", text, token.text)));
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self, token: Token) -> Result<(), String> {
+        match token.text.as_str() {
+            ":" => {
+                let name = self.name()?;
+                self.define_label(name, 0)?;
+            }
+            ":next" => {
+                // the second byte of the next instruction, for self-modifying code
+                let name = self.name()?;
+                self.define_label(name, 1)?;
+            }
+            ":const" => {
+                let name = self.name()?;
+                let value = self.value()?;
+                self.constants.insert(name, value);
+            }
+            ":calc" => {
+                let name = self.name()?;
+                self.expect("{")?;
+                let value = self.calc()?;
+                self.constants.insert(name, value);
+            }
+            ":alias" => {
+                let name = self.name()?;
+                let register = self.register()?;
+                self.aliases.insert(name, register);
+            }
+            ":unpack" => {
+                let high = match self.peek() {
+                    Some("long") => {
+                        self.next()?;
+                        None
+                    }
+                    _ => Some(self.nibble()?),
+                };
+                let target = self.next()?;
+                let address = self.here;
+                self.instruction(0x6000)?;
+                self.instruction(0x6100)?;
+                self.refer(address, &target.text, Patch::Unpack(high))?;
+            }
+            ":org" => {
+                let address = self.value()? as i64;
+                if !(START as i64..=0xFFFF).contains(&address) {
+                    return Err(self.error(&format!("can't put code at {:#X}", address)));
+                }
+                self.here = address as u32;
+            }
+            ":breakpoint" => {
+                self.next()?;
+            }
+            ":monitor" => {
+                self.next()?;
+                self.next()?;
+            }
+            ":macro" => self.define_macro()?,
+            ":byte" => {
+                let byte = self.byte()?;
+                self.emit(byte)?;
+            }
+            ":pointer" => {
+                let target = self.next()?;
+                let address = self.here;
+                self.instruction(0)?;
+                self.refer(address, &target.text, Patch::Long)?;
+            }
+            ":call" => {
+                let target = self.next()?;
+                self.jump_to_name(0x2000, &target.text)?;
+            }
+            ":assert" => {
+                let message = match self.peek() {
+                    Some(text) if text.starts_with('"') => {
+                        self.next()?.text.trim_matches('"').to_string()
+                    }
+                    _ => String::from("assertion failed"),
+                };
+                self.expect("{")?;
+                if self.calc()? == 0.0 {
+                    return Err(self.error(&message));
+                }
+            }
+            ";" | "return" => self.instruction(0x00EE)?,
+            "clear" => self.instruction(0x00E0)?,
+            "hires" => self.instruction(0x00FF)?,
+            "lores" => self.instruction(0x00FE)?,
+            "scroll-right" => self.instruction(0x00FB)?,
+            "scroll-left" => self.instruction(0x00FC)?,
+            "exit" => self.instruction(0x00FD)?,
+            "audio" => self.instruction(0xF002)?,
+            "scroll-down" => {
+                let rows = self.nibble()? as u16;
+                self.instruction(0x00C0 | rows)?;
+            }
+            "scroll-up" => {
+                let rows = self.nibble()? as u16;
+                self.instruction(0x00D0 | rows)?;
+            }
+            "plane" => {
+                let planes = self.nibble()? as u16;
+                self.instruction(0xF001 | planes << 8)?;
+            }
+            "bcd" => self.register_instruction(0xF033)?,
+            "saveflags" => self.register_instruction(0xF075)?,
+            "loadflags" => self.register_instruction(0xF085)?,
+            "save" | "load" => {
+                let x = self.register()? as u16;
+                let (single, range) = match token.text.as_str() {
+                    "save" => (0xF055, 0x5002),
+                    _ => (0xF065, 0x5003),
+                };
+                if self.peek() == Some("-") {
+                    self.next()?;
+                    let y = self.register()? as u16;
+                    self.instruction(range | x << 8 | y << 4)?;
+                } else {
+                    self.instruction(single | x << 8)?;
+                }
+            }
+            "sprite" => {
+                let x = self.register()? as u16;
+                let y = self.register()? as u16;
+                let height = self.nibble()? as u16;
+                self.instruction(0xD000 | x << 8 | y << 4 | height)?;
+            }
+            "jump" => {
+                let target = self.next()?;
+                self.jump_to_name(0x1000, &target.text)?;
+            }
+            "jump0" => {
+                let target = self.next()?;
+                self.jump_to_name(0xB000, &target.text)?;
+            }
+            "native" => {
+                let target = self.next()?;
+                self.jump_to_name(0x0000, &target.text)?;
+            }
+            "i" => self.index_statement()?,
+            "delay" => self.timer_statement(0xF015)?,
+            "buzzer" => self.timer_statement(0xF018)?,
+            "pitch" => self.timer_statement(0xF03A)?,
+            "if" => self.if_statement()?,
+            "else" => match self.blocks.pop() {
+                Some(Block::If {
+                    jump,
+                    has_else: false,
+                }) => {
+                    let else_jump = self.here;
+                    self.instruction(0x1000)?;
+                    self.patch(jump, self.here as f64, Patch::Address)?;
+                    self.blocks.push(Block::If {
+                        jump: else_jump,
+                        has_else: true,
+                    });
+                }
+                _ => return Err(self.error("else without an if ... begin")),
+            },
+            "end" => match self.blocks.pop() {
+                Some(Block::If { jump, .. }) => {
+                    self.patch(jump, self.here as f64, Patch::Address)?
+                }
+                _ => return Err(self.error("end without an if ... begin")),
+            },
+            "loop" => self.blocks.push(Block::Loop {
+                start: self.here,
+                exits: Vec::new(),
+            }),
+            "while" => {
+                let condition = self.condition()?;
+                self.skip_unless(&condition, true)?;
+                let exit = self.here;
+                self.instruction(0x1000)?;
+                let innermost_loop = self.blocks.iter_mut().rev().find_map(|block| match block {
+                    Block::Loop { exits, .. } => Some(exits),
+                    Block::If { .. } => None,
+                });
+                match innermost_loop {
+                    Some(exits) => exits.push(exit),
+                    None => return Err(self.error("while outside of a loop")),
+                }
+            }
+            "again" => match self.blocks.pop() {
+                Some(Block::Loop { start, exits }) => {
+                    let address = self.here;
+                    self.instruction(0x1000)?;
+                    self.patch(address, start as f64, Patch::Address)?;
+                    for exit in exits {
+                        self.patch(exit, self.here as f64, Patch::Address)?;
+                    }
+                }
+                _ => return Err(self.error("again without a loop")),
+            },
+            text => {
+                if let Some(register) = self.register_named(text) {
+                    self.assignment(register)?;
+                } else if parse_number(text).is_some() {
+                    let byte = self.to_byte(parse_number(text).unwrap_or_default())?;
+                    self.emit(byte)?;
+                } else if self.macros.contains_key(text) {
+                    self.expand(text)?;
+                } else if text.starts_with([':', '{', '}']) {
+                    return Err(self.error(&format!("'{}' isn't supported", text)));
+                } else {
+                    // anything else is a call to a subroutine
+                    self.jump_to_name(0x2000, text)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn name(&mut self) -> Result<String, String> {
+        let token = self.next()?;
+        if parse_number(&token.text).is_some() || self.register_named(&token.text).is_some() {
+            return Err(self.error(&format!("'{}' can't be used as a name", token.text)));
+        }
+        Ok(token.text)
+    }
+
+    // octo starts at main, which needs a jump in front of it unless it's the
+    // first thing in the program
+    fn reserve_main_jump(&mut self) {
+        if !self.rom.is_empty() || self.labels.contains_key("main") {
+            return;
+        }
+        self.rom.extend([0x10, 0x00]);
+        self.fixups.push(Fixup {
+            address: START,
+            name: String::from("main"),
+            patch: Patch::Address,
+            line: self.line,
+        });
+        if self.here == START {
+            self.here += 2;
+        }
+    }
+
+    // a label at here, or this many bytes past it
+    fn define_label(&mut self, name: String, offset: u32) -> Result<(), String> {
+        if name != "main" {
+            self.reserve_main_jump();
+        }
+        if self
+            .labels
+            .insert(name.clone(), self.here + offset)
+            .is_some()
+        {
+            return Err(self.error(&format!("'{}' is defined twice", name)));
+        }
+        Ok(())
+    }
+
+    fn register_named(&self, text: &str) -> Option<u8> {
+        if let Some(register) = self.aliases.get(text) {
+            return Some(*register);
+        }
+        let digit = text.strip_prefix(['v', 'V'])?;
+        if digit.len() != 1 {
+            return None;
+        }
+        u8::from_str_radix(digit, 16).ok()
+    }
+
+    fn register(&mut self) -> Result<u8, String> {
+        let token = self.next()?;
+        self.register_named(&token.text)
+            .ok_or_else(|| self.error(&format!("expected a register, got '{}'", token.text)))
+    }
+
+    // a number, a constant, a label that's already defined or a { :calc }
+    fn value(&mut self) -> Result<f64, String> {
+        let token = self.next()?;
+        if token.text == "{" {
+            return self.calc();
+        }
+        self.lookup(&token.text)
+            .ok_or_else(|| self.error(&format!("expected a number, got '{}'", token.text)))
+    }
+
+    fn lookup(&self, text: &str) -> Option<f64> {
+        parse_number(text)
+            .or_else(|| self.constants.get(text).copied())
+            .or_else(|| self.labels.get(text).map(|address| *address as f64))
+    }
+
+    fn to_byte(&self, value: f64) -> Result<u8, String> {
+        let value = value.floor() as i64;
+        if !(-128..=255).contains(&value) {
+            return Err(self.error(&format!("{} does not fit in a byte", value)));
+        }
+        Ok(value as u8)
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        let value = self.value()?;
+        self.to_byte(value)
+    }
+
+    fn nibble(&mut self) -> Result<u8, String> {
+        let value = self.value()?.floor() as i64;
+        if !(0..=15).contains(&value) {
+            return Err(self.error(&format!("{} does not fit in 4 bits", value)));
+        }
+        Ok(value as u8)
+    }
+
+    fn emit(&mut self, byte: u8) -> Result<(), String> {
+        self.reserve_main_jump();
+        if self.here > 0xFFFF {
+            return Err(self.error("the program doesn't fit in memory"));
+        }
+        let index = (self.here - START) as usize;
+        if self.rom.len() <= index {
+            self.rom.resize(index + 1, 0);
+        }
+        self.rom[index] = byte;
+        self.here += 1;
+        Ok(())
+    }
+
+    fn instruction(&mut self, instruction: u16) -> Result<(), String> {
+        self.emit((instruction >> 8) as u8)?;
+        self.emit(instruction as u8)
+    }
+
+    fn register_instruction(&mut self, instruction: u16) -> Result<(), String> {
+        let x = self.register()? as u16;
+        self.instruction(instruction | x << 8)
+    }
+
+    fn timer_statement(&mut self, instruction: u16) -> Result<(), String> {
+        self.expect(":=")?;
+        self.register_instruction(instruction)
+    }
+
+    // an instruction ending in an address, which might not be known yet
+    fn jump_to_name(&mut self, instruction: u16, name: &str) -> Result<(), String> {
+        let address = self.here;
+        self.instruction(instruction)?;
+        self.refer(address, name, Patch::Address)
+    }
+
+    fn refer(&mut self, address: u32, name: &str, patch: Patch) -> Result<(), String> {
+        match self.lookup(name) {
+            Some(value) => self.patch(address, value, patch),
+            None => {
+                self.fixups.push(Fixup {
+                    address,
+                    name: name.to_string(),
+                    patch,
+                    line: self.line,
+                });
+                Ok(())
+            }
+        }
+    }
+
+    fn patch(&mut self, address: u32, value: f64, patch: Patch) -> Result<(), String> {
+        let value = value.floor() as i64;
+        let limit = match patch {
+            Patch::Long | Patch::Unpack(None) => 0xFFFF,
+            Patch::Address | Patch::Unpack(Some(_)) => 0xFFF,
+        };
+        if !(0..=limit).contains(&value) {
+            return Err(self.error(&format!("address {:#X} is out of range", value)));
+        }
+        let index = (address - START) as usize;
+        let (high, low) = ((value >> 8) as u8, value as u8);
+        match patch {
+            Patch::Address => {
+                self.rom[index] = (self.rom[index] & 0xF0) | high;
+                self.rom[index + 1] = low;
+            }
+            Patch::Long => {
+                self.rom[index] = high;
+                self.rom[index + 1] = low;
+            }
+            Patch::Unpack(nibble) => {
+                self.rom[index + 1] = nibble.map_or(high, |nibble| nibble << 4 | high);
+                self.rom[index + 3] = low;
+            }
+        }
+        Ok(())
+    }
+
+    fn define_macro(&mut self) -> Result<(), String> {
+        let name = self.name()?;
+        let mut arguments = Vec::new();
+        loop {
+            let token = self.next()?;
+            if token.text == "{" {
+                break;
+            }
+            arguments.push(token.text);
+        }
+
+        let mut body = Vec::new();
+        let mut depth = 1;
+        loop {
+            let token = self.next()?;
+            match token.text.as_str() {
+                "{" => depth += 1,
+                "}" => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 {
+                break;
+            }
+            body.push(token);
+        }
+        self.macros.insert(name, Macro { arguments, body });
+        Ok(())
+    }
+
+    // puts the macro's body in front of the rest of the program, with the
+    // arguments swapped in
+    fn expand(&mut self, name: &str) -> Result<(), String> {
+        self.expansions += 1;
+        if self.expansions > MAX_EXPANSIONS {
+            return Err(self.error(&format!("macro '{}' never stops expanding", name)));
+        }
+
+        let found = self.macros[name].clone();
+        let mut values = Vec::new();
+        for _ in &found.arguments {
+            values.push(self.next()?.text);
+        }
+        for token in found.body.iter().rev() {
+            let text = match found.arguments.iter().position(|arg| *arg == token.text) {
+                Some(index) => values[index].clone(),
+                None => token.text.clone(),
+            };
+            self.tokens.push_front(Token {
+                text,
+                line: self.line,
+            });
+        }
+        Ok(())
+    }
+
+    // octo's :calc has no precedence, everything is evaluated right to left
+    // so 2 * 3 + 1 is 8. the opening { has already been read
+    fn calc(&mut self) -> Result<f64, String> {
+        let value = self.calc_expression()?;
+        self.expect("}")?;
+        Ok(value)
+    }
+
+    fn calc_expression(&mut self) -> Result<f64, String> {
+        let left = self.calc_term()?;
+        let operator = match self.peek() {
+            Some(
+                operator @ ("+" | "-" | "*" | "/" | "%" | "&" | "|" | "^" | "<<" | ">>" | "pow"
+                | "min" | "max" | "<" | ">" | "<=" | ">=" | "==" | "!="),
+            ) => operator.to_string(),
+            _ => return Ok(left),
+        };
+        self.next()?;
+        let right = self.calc_expression()?;
+
+        let (a, b) = (left as i64, right as i64);
+        Ok(match operator.as_str() {
+            "+" => left + right,
+            "-" => left - right,
+            "*" => left * right,
+            "/" => left / right,
+            "%" => left % right,
+            "&" => (a & b) as f64,
+            "|" => (a | b) as f64,
+            "^" => (a ^ b) as f64,
+            "<<" => a.checked_shl(b as u32).unwrap_or(0) as f64,
+            ">>" => a.checked_shr(b as u32).unwrap_or(0) as f64,
+            "pow" => left.powf(right),
+            "min" => left.min(right),
+            "max" => left.max(right),
+            "<" => (left < right) as u8 as f64,
+            ">" => (left > right) as u8 as f64,
+            "<=" => (left <= right) as u8 as f64,
+            ">=" => (left >= right) as u8 as f64,
+            "==" => (left == right) as u8 as f64,
+            _ => (left != right) as u8 as f64,
+        })
+    }
+
+    fn calc_term(&mut self) -> Result<f64, String> {
+        let token = self.next()?;
+        let function: fn(f64) -> f64 = match token.text.as_str() {
+            "(" => {
+                let value = self.calc_expression()?;
+                self.expect(")")?;
+                return Ok(value);
+            }
+            "HERE" => return Ok(self.here as f64),
+            "PI" => return Ok(std::f64::consts::PI),
+            "E" => return Ok(std::f64::consts::E),
+            "-" => |value| -value,
+            "~" => |value| !(value as i64) as f64,
+            "!" => |value| (value == 0.0) as u8 as f64,
+            "sin" => f64::sin,
+            "cos" => f64::cos,
+            "tan" => f64::tan,
+            "exp" => f64::exp,
+            "log" => f64::ln,
+            "abs" => f64::abs,
+            "sqrt" => f64::sqrt,
+            "sign" => f64::signum,
+            "ceil" => f64::ceil,
+            "floor" => f64::floor,
+            "@" => {
+                // a byte of the program compiled so far
+                let address = self.calc_term()? as i64 - START as i64;
+                let byte = usize::try_from(address)
+                    .ok()
+                    .and_then(|index| self.rom.get(index));
+                return Ok(byte.copied().unwrap_or(0) as f64);
+            }
+            text => {
+                return self
+                    .lookup(text)
+                    .ok_or_else(|| self.error(&format!("unknown name '{}' in :calc", text)))
+            }
+        };
+        Ok(function(self.calc_term()?))
+    }
+
+    fn index_statement(&mut self) -> Result<(), String> {
+        let operator = self.next()?;
+        match (operator.text.as_str(), self.peek()) {
+            (":=", Some("hex")) => {
+                self.next()?;
+                self.register_instruction(0xF029)
+            }
+            (":=", Some("bighex")) => {
+                self.next()?;
+                self.register_instruction(0xF030)
+            }
+            (":=", Some("long")) => {
+                self.next()?;
+                let target = self.next()?;
+                self.instruction(0xF000)?;
+                let address = self.here;
+                self.instruction(0)?;
+                self.refer(address, &target.text, Patch::Long)
+            }
+            (":=", _) => {
+                let target = self.next()?;
+                self.jump_to_name(0xA000, &target.text)
+            }
+            ("+=", _) => self.register_instruction(0xF01E),
+            (text, _) => Err(self.error(&format!("expected := or += after i, got '{}'", text))),
+        }
+    }
+
+    fn operand(&mut self) -> Result<Operand, String> {
+        match self.peek().and_then(|text| self.register_named(text)) {
+            Some(register) => {
+                self.next()?;
+                Ok(Operand::Register(register))
+            }
+            None => Ok(Operand::Value(self.byte()?)),
+        }
+    }
+
+    fn assignment(&mut self, x: u8) -> Result<(), String> {
+        let x = (x as u16) << 8;
+        let operator = self.next()?;
+        let instruction = match operator.text.as_str() {
+            ":=" => match self.peek() {
+                Some("random") => {
+                    self.next()?;
+                    0xC000 | x | self.byte()? as u16
+                }
+                Some("key") => {
+                    self.next()?;
+                    0xF00A | x
+                }
+                Some("delay") => {
+                    self.next()?;
+                    0xF007 | x
+                }
+                _ => match self.operand()? {
+                    Operand::Register(y) => 0x8000 | x | (y as u16) << 4,
+                    Operand::Value(value) => 0x6000 | x | value as u16,
+                },
+            },
+            "+=" => match self.operand()? {
+                Operand::Register(y) => 0x8004 | x | (y as u16) << 4,
+                Operand::Value(value) => 0x7000 | x | value as u16,
+            },
+            // there's no subtract immediate, adding the negative does the same
+            "-=" => match self.operand()? {
+                Operand::Register(y) => 0x8005 | x | (y as u16) << 4,
+                Operand::Value(value) => 0x7000 | x | value.wrapping_neg() as u16,
+            },
+            text => {
+                let low = match text {
+                    "|=" => 0x1,
+                    "&=" => 0x2,
+                    "^=" => 0x3,
+                    ">>=" => 0x6,
+                    "=-" => 0x7,
+                    "<<=" => 0xE,
+                    _ => return Err(self.error(&format!("unknown operator '{}'", text))),
+                };
+                0x8000 | x | (self.register()? as u16) << 4 | low
+            }
+        };
+        self.instruction(instruction)
+    }
+
+    fn condition(&mut self) -> Result<Condition, String> {
+        let register = self.register()?;
+        let comparison = self.next()?.text;
+        let operand = match comparison.as_str() {
+            "key" | "-key" => None,
+            "==" | "!=" | "<" | ">" | "<=" | ">=" => Some(self.operand()?),
+            _ => return Err(self.error(&format!("unknown comparison '{}'", comparison))),
+        };
+        Ok(Condition {
+            register,
+            comparison,
+            operand,
+        })
+    }
+
+    fn if_statement(&mut self) -> Result<(), String> {
+        let condition = self.condition()?;
+        let token = self.next()?;
+        match token.text.as_str() {
+            // the next statement is skipped when the condition doesn't hold
+            "then" => self.skip_unless(&condition, false),
+            // a jump over the block that's skipped when it does
+            "begin" => {
+                self.skip_unless(&condition, true)?;
+                self.blocks.push(Block::If {
+                    jump: self.here,
+                    has_else: false,
+                });
+                self.instruction(0x1000)
+            }
+            text => Err(self.error(&format!("expected then or begin, got '{}'", text))),
+        }
+    }
+
+    // skips the next instruction unless the condition holds, or unless it
+    // doesn't when negated. <, >, <= and >= subtract into vf to compare
+    fn skip_unless(&mut self, condition: &Condition, negated: bool) -> Result<(), String> {
+        let comparison = match (negated, condition.comparison.as_str()) {
+            (false, comparison) => comparison,
+            (true, "==") => "!=",
+            (true, "!=") => "==",
+            (true, "key") => "-key",
+            (true, "-key") => "key",
+            (true, "<") => ">=",
+            (true, ">") => "<=",
+            (true, ">=") => "<",
+            (true, _) => ">",
+        };
+        let register = condition.register as u16;
+        let x = register << 8;
+        let operand = condition.operand.unwrap_or(Operand::Value(0));
+        let load_vf = match operand {
+            Operand::Register(y) => 0x8F00 | (y as u16) << 4,
+            Operand::Value(value) => 0x6F00 | value as u16,
+        };
+        let instructions = match (comparison, operand) {
+            ("==", Operand::Register(y)) => vec![0x9000 | x | (y as u16) << 4],
+            ("==", Operand::Value(value)) => vec![0x4000 | x | value as u16],
+            ("!=", Operand::Register(y)) => vec![0x5000 | x | (y as u16) << 4],
+            ("!=", Operand::Value(value)) => vec![0x3000 | x | value as u16],
+            ("key", _) => vec![0xE0A1 | x],
+            ("-key", _) => vec![0xE09E | x],
+            (">", _) => vec![load_vf, 0x8F05 | register << 4, 0x3F01],
+            ("<", _) => vec![load_vf, 0x8F07 | register << 4, 0x3F01],
+            (">=", _) => vec![load_vf, 0x8F07 | register << 4, 0x4F01],
+            (_, _) => vec![load_vf, 0x8F05 | register << 4, 0x4F01],
+        };
+        for instruction in instructions {
+            self.instruction(instruction)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn compile_octo_test() {
+        let bounce = r#"
+            # bounce a ball along the top of the screen
+            :const SPEED 2
+            :alias ball-x v0
+            :alias ball-y v1
+
+            : ball
+              0b11000000
+              0b11000000
+
+            : main
+              ball-x := 10
+              ball-y := 4
+              i := ball
+              loop
+                sprite ball-x ball-y 2
+                if ball-x == 62 then ball-x := 0
+                ball-x += SPEED
+                if v2 -key then jump main
+              again
+        "#;
+        assert_eq!(
+            vec![
+                0x12, 0x04, 0xC0, 0xC0, 0x60, 0x0A, 0x61, 0x04, 0xA2, 0x02, 0xD0, 0x12, 0x40, 0x3E,
+                0x60, 0x00, 0x70, 0x02, 0xE2, 0x9E, 0x12, 0x04, 0x12, 0x0A
+            ],
+            compile(bounce).unwrap()
+        );
+
+        let blocks = r#"
+            :macro twice reg { reg += 1 reg += 1 }
+            :calc ROW { 8 * 2 + 1 }
+            : main
+              twice v3
+              v4 := ROW
+              if v3 > 1 begin v5 := 1 else v5 := 2 end
+              loop
+                while v6 != 3
+                v6 += 1
+              again
+              :unpack 0xA data
+              i := data
+              jump main
+            : data 1 2
+        "#;
+        assert_eq!(
+            vec![
+                0x73, 0x01, 0x73, 0x01, 0x64, 0x18, 0x6F, 0x01, 0x8F, 0x35, 0x4F, 0x01, 0x12, 0x12,
+                0x65, 0x01, 0x12, 0x14, 0x65, 0x02, 0x46, 0x03, 0x12, 0x1C, 0x76, 0x01, 0x12, 0x14,
+                0x60, 0xA2, 0x61, 0x24, 0xA2, 0x24, 0x12, 0x00, 0x01, 0x02
+            ],
+            compile(blocks).unwrap()
+        );
+
+        assert_eq!(
+            "line 1: 'nowhere' is never defined",
+            compile(": main jump nowhere").unwrap_err()
+        );
+        assert!(compile(": main loop").is_err());
+        assert!(compile("clear").is_err());
+    }
+}
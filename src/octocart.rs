@@ -0,0 +1,203 @@
+use crate::cli::parse_color;
+use crate::octo;
+use crate::quirks::Quirks;
+use crate::settings::Settings;
+use serde::Deserialize;
+
+// octocarts are gifs with a json payload hidden in the low nibble of every
+// pixel's palette index, high nibble first, across all frames in order. the
+// payload starts with its length as a 4 byte big-endian number and looks like
+// {"program": "...", "options": {"tickrate": 20, "shiftQuirks": false, ...}}
+pub struct Octocart {
+    pub program: Vec<u8>,
+    pub options: OctoOptions,
+}
+
+#[derive(Deserialize)]
+struct CartPayload {
+    program: CartProgram,
+    #[serde(default)]
+    options: OctoOptions,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CartProgram {
+    Binary(Vec<u8>),
+    Source(String),
+}
+
+// the subset of octo's options we know how to honour
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct OctoOptions {
+    pub tickrate: Option<u32>,
+    pub fill_color: Option<String>,
+    pub fill_color2: Option<String>,
+    pub blend_color: Option<String>,
+    pub background_color: Option<String>,
+    pub shift_quirks: Option<bool>,
+    pub load_store_quirks: Option<bool>,
+    pub clip_quirks: Option<bool>,
+    pub jump_quirks: Option<bool>,
+    pub logic_quirks: Option<bool>,
+}
+
+impl Octocart {
+    pub fn load(gif_bytes: &[u8]) -> Result<Octocart, String> {
+        let payload = Octocart::extract_payload(gif_bytes)?;
+        let cart: CartPayload = serde_json::from_slice(&payload)
+            .map_err(|e| format!("octocart payload is not valid: {}", e))?;
+
+        let program = match cart.program {
+            CartProgram::Binary(bytes) => bytes,
+            CartProgram::Source(source) => octo::compile(&source)
+                .map_err(|e| format!("could not compile the octocart's octo source: {}", e))?,
+        };
+
+        Ok(Octocart {
+            program,
+            options: cart.options,
+        })
+    }
+
+    fn extract_payload(gif_bytes: &[u8]) -> Result<Vec<u8>, String> {
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::Indexed);
+        let mut decoder = options
+            .read_info(gif_bytes)
+            .map_err(|e| format!("not a gif: {}", e))?;
+
+        let mut nibbles = Vec::new();
+        while let Some(frame) = decoder
+            .read_next_frame()
+            .map_err(|e| format!("broken gif frame: {}", e))?
+        {
+            nibbles.extend(frame.buffer.iter().map(|index| index & 0x0F));
+        }
+
+        let bytes: Vec<u8> = nibbles
+            .chunks_exact(2)
+            .map(|pair| (pair[0] << 4) | pair[1])
+            .collect();
+        if bytes.len() < 4 {
+            return Err(String::from("gif is too small to be an octocart"));
+        }
+
+        let size = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
+        bytes
+            .get(4..4 + size)
+            .map(|payload| payload.to_vec())
+            .ok_or_else(|| String::from("octocart payload is truncated"))
+    }
+}
+
+impl OctoOptions {
+    pub fn apply(&self, settings: &mut Settings) -> Result<(), String> {
+        if let Some(tickrate) = self.tickrate {
+            settings.instructions_per_frame = tickrate.max(1);
+        }
+        // same order as our palette: background, plane 1, plane 2, both
+        let colors = [
+            &self.background_color,
+            &self.fill_color,
+            &self.fill_color2,
+            &self.blend_color,
+        ];
+        for (index, color) in colors.into_iter().enumerate() {
+            if let Some(color) = color {
+                settings.palette.colors[index] = parse_color(color)?;
+            }
+        }
+
+        // octo's quirk flags are mostly phrased the same way ours are,
+        // loadStoreQuirks is the odd one out: true means I is left alone
+        let mut quirks: Quirks = settings.effective_quirks();
+        if let Some(shift_quirks) = self.shift_quirks {
+            quirks.shift_vx = shift_quirks;
+        }
+        if let Some(load_store_quirks) = self.load_store_quirks {
+            quirks.memory_increment = !load_store_quirks;
+        }
+        if let Some(clip_quirks) = self.clip_quirks {
+            quirks.clipping = clip_quirks;
+        }
+        if let Some(jump_quirks) = self.jump_quirks {
+            quirks.jump_vx = jump_quirks;
+        }
+        if let Some(logic_quirks) = self.logic_quirks {
+            quirks.vf_reset = logic_quirks;
+        }
+        if quirks != settings.effective_quirks() {
+            settings.custom_quirks = Some(quirks);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::quirks::QuirkPreset;
+
+    // the reverse of extract_payload
+    fn build_cart(payload: &str) -> Vec<u8> {
+        let mut bytes = (payload.len() as u32).to_be_bytes().to_vec();
+        bytes.extend(payload.as_bytes());
+        let mut pixels: Vec<u8> = bytes
+            .iter()
+            .flat_map(|byte| [byte >> 4, byte & 0x0F])
+            .collect();
+        pixels.resize(160 * 128, 0);
+
+        let palette: Vec<u8> = (0..64).flat_map(|index| [index * 4, 0, 0]).collect();
+        let mut gif_bytes = Vec::new();
+        {
+            let mut encoder = gif::Encoder::new(&mut gif_bytes, 160, 128, &palette).unwrap();
+            let frame = gif::Frame::from_indexed_pixels(160, 128, pixels, None);
+            encoder.write_frame(&frame).unwrap();
+        }
+        gif_bytes
+    }
+
+    #[test]
+    fn load_octocart_source_and_options_test() {
+        let cart = build_cart(
+            r##"{"program": ": main\n  clear\n  loop again", "options": {"tickrate": 30, "fillColor": "#FFCC00", "shiftQuirks": true}}"##,
+        );
+
+        let octocart = Octocart::load(&cart).unwrap();
+        let mut settings = Settings::default();
+        octocart.options.apply(&mut settings).unwrap();
+
+        assert_eq!(vec![0x00, 0xE0, 0x12, 0x02], octocart.program);
+        assert_eq!(30, settings.instructions_per_frame);
+        assert_eq!((0xFF, 0xCC, 0x00), settings.palette.colors[1]);
+        assert!(settings.effective_quirks().shift_vx);
+    }
+
+    #[test]
+    fn load_store_quirks_leave_i_alone_test() {
+        // in octo, loadStoreQuirks means load and save don't move i, like the
+        // super-chip, while our memory_increment is the vip moving it
+        let options = |json: &str| serde_json::from_str::<OctoOptions>(json).unwrap();
+
+        let mut settings = Settings::default();
+        assert!(settings.effective_quirks().memory_increment);
+        options(r#"{"loadStoreQuirks": true}"#)
+            .apply(&mut settings)
+            .unwrap();
+        assert!(!settings.effective_quirks().memory_increment);
+
+        let mut settings = Settings {
+            quirks: QuirkPreset::Schip,
+            ..Settings::default()
+        };
+        options(r#"{"loadStoreQuirks": false}"#)
+            .apply(&mut settings)
+            .unwrap();
+        assert!(settings.effective_quirks().memory_increment);
+    }
+}
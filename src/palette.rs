@@ -0,0 +1,93 @@
+use crate::settings::Rgb;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+pub const BACKGROUND: usize = 0;
+pub const FOREGROUND: usize = 1;
+
+// background, plane 1, plane 2, and both planes lit. plain chip-8 only ever
+// uses the first two, xo-chip style multi-plane roms need all four
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    pub colors: [Rgb; 4],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    // white on black
+    Classic,
+    // green phosphor crt
+    Green,
+    // amber monochrome monitor
+    Amber,
+    // original game boy screen
+    Lcd,
+    // octo's yellow on brown
+    Octo,
+}
+
+const THEMES: [Theme; 5] = [
+    Theme::Classic,
+    Theme::Green,
+    Theme::Amber,
+    Theme::Lcd,
+    Theme::Octo,
+];
+
+impl Theme {
+    pub fn palette(self) -> Palette {
+        let colors = match self {
+            Theme::Classic => [
+                (0x00, 0x00, 0x00),
+                (0xFF, 0xFF, 0xFF),
+                (0xAA, 0xAA, 0xAA),
+                (0x55, 0x55, 0x55),
+            ],
+            Theme::Green => [
+                (0x00, 0x11, 0x00),
+                (0x33, 0xFF, 0x33),
+                (0x1F, 0x9F, 0x1F),
+                (0x0F, 0x4F, 0x0F),
+            ],
+            Theme::Amber => [
+                (0x1A, 0x0F, 0x00),
+                (0xFF, 0xB0, 0x00),
+                (0xB3, 0x7B, 0x00),
+                (0x5C, 0x3F, 0x00),
+            ],
+            Theme::Lcd => [
+                (0x9B, 0xBC, 0x0F),
+                (0x0F, 0x38, 0x0F),
+                (0x30, 0x62, 0x30),
+                (0x8B, 0xAC, 0x0F),
+            ],
+            Theme::Octo => [
+                (0x99, 0x66, 0x00),
+                (0xFF, 0xCC, 0x00),
+                (0xFF, 0x66, 0x00),
+                (0x66, 0x22, 0x00),
+            ],
+        };
+
+        Palette { colors }
+    }
+
+    // for the hotkey, wraps back around to the first theme
+    pub fn next(self) -> Theme {
+        let index = THEMES.iter().position(|theme| *theme == self).unwrap_or(0);
+        THEMES[(index + 1) % THEMES.len()]
+    }
+
+    pub fn name(self) -> String {
+        self.to_possible_value()
+            .map(|value| value.get_name().to_string())
+            .unwrap_or_default()
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Palette {
+        Theme::Classic.palette()
+    }
+}
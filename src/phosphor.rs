@@ -0,0 +1,120 @@
+use crate::pixel::Pixel;
+use crate::settings::Rgb;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+const WIDTH: usize = 64;
+const HEIGHT: usize = 32;
+
+// anything dimmer than this is treated as fully off, so the fade actually ends
+const CUTOFF: f32 = 0.02;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Persistence {
+    // pixels switch on and off instantly, flicker and all
+    Off,
+    // lit pixels fade out over several frames like an old crt
+    Phosphor,
+    // every frame is averaged with the one before it
+    Blend,
+}
+
+// games erase and redraw sprites with xor, so a moving sprite is missing from
+// every other frame. keeping some of the old brightness around hides that
+pub struct Phosphor {
+    mode: Persistence,
+    // how much of a pixel's brightness is kept each frame once it's switched off
+    keep: f32,
+    brightness: Vec<f32>,
+    previous: Vec<bool>,
+}
+
+impl Phosphor {
+    // decay is the percentage of brightness lost each frame
+    pub fn new(mode: Persistence, decay: u32) -> Phosphor {
+        Phosphor {
+            mode,
+            keep: 1.0 - decay.clamp(1, 100) as f32 / 100.0,
+            brightness: vec![0.0; WIDTH * HEIGHT],
+            previous: vec![false; WIDTH * HEIGHT],
+        }
+    }
+
+    // call once per emulated frame. returns true if anything on screen changed
+    pub fn update(&mut self, vram: &[Vec<Pixel>]) -> bool {
+        let mut changed = false;
+        for pixel in vram.iter().flatten() {
+            let index = pixel.y as usize * WIDTH + pixel.x as usize;
+            let old = self.brightness[index];
+            let new = match self.mode {
+                Persistence::Off => f32::from(u8::from(pixel.on)),
+                Persistence::Phosphor if pixel.on => 1.0,
+                Persistence::Phosphor if old * self.keep < CUTOFF => 0.0,
+                Persistence::Phosphor => old * self.keep,
+                Persistence::Blend => {
+                    (f32::from(u8::from(pixel.on)) + f32::from(u8::from(self.previous[index])))
+                        / 2.0
+                }
+            };
+
+            self.previous[index] = pixel.on;
+            self.brightness[index] = new;
+            changed |= new != old;
+        }
+
+        changed
+    }
+
+    pub fn brightness(&self, x: usize, y: usize) -> f32 {
+        self.brightness[y * WIDTH + x]
+    }
+}
+
+// mixes the background and foreground, 0.0 is all background
+pub fn blend(background: Rgb, foreground: Rgb, brightness: f32) -> Rgb {
+    let mix =
+        |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * brightness).round() as u8;
+    (
+        mix(background.0, foreground.0),
+        mix(background.1, foreground.1),
+        mix(background.2, foreground.2),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn vram_with(on: bool) -> Vec<Vec<Pixel>> {
+        (0..HEIGHT as u32)
+            .map(|y| {
+                (0..WIDTH as u32)
+                    .map(|x| Pixel::new(x, y, on && x == 3 && y == 2))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn phosphor_fades_out_over_frames_test() {
+        let mut phosphor = Phosphor::new(Persistence::Phosphor, 50);
+
+        assert!(phosphor.update(&vram_with(true)));
+        assert_eq!(1.0, phosphor.brightness(3, 2));
+
+        assert!(phosphor.update(&vram_with(false)));
+        assert_eq!(0.5, phosphor.brightness(3, 2));
+        assert!(phosphor.update(&vram_with(false)));
+        assert_eq!(0.25, phosphor.brightness(3, 2));
+
+        // settles at fully off and then stops asking for redraws
+        while phosphor.update(&vram_with(false)) {}
+        assert_eq!(0.0, phosphor.brightness(3, 2));
+        assert_eq!(
+            (0x80, 0x80, 0x80),
+            blend((0, 0, 0), (0xFF, 0xFF, 0xFF), 0.5)
+        );
+    }
+}
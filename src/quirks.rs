@@ -0,0 +1,63 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+// the different chip-8 flavours disagree on a handful of instructions.
+// these are the ones timendus' quirks test (roms/5-quirks.ch8) checks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Quirks {
+    // 8XY1, 8XY2 and 8XY3 reset VF to 0
+    pub vf_reset: bool,
+    // FX55 and FX65 leave I pointing past the last register
+    pub memory_increment: bool,
+    // 8XY6 and 8XYE shift Vx in place instead of copying Vy first
+    pub shift_vx: bool,
+    // BNNN becomes BXNN and jumps to XNN + Vx
+    pub jump_vx: bool,
+    // sprites get cut off at the screen edge instead of wrapping around
+    pub clipping: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuirkPreset {
+    // the original cosmac vip interpreter
+    Chip8,
+    // super-chip 1.1 on the hp48
+    Schip,
+    // octo's xo-chip
+    Xochip,
+}
+
+impl QuirkPreset {
+    pub fn quirks(self) -> Quirks {
+        match self {
+            QuirkPreset::Chip8 => Quirks {
+                vf_reset: true,
+                memory_increment: true,
+                shift_vx: false,
+                jump_vx: false,
+                clipping: true,
+            },
+            QuirkPreset::Schip => Quirks {
+                vf_reset: false,
+                memory_increment: false,
+                shift_vx: true,
+                jump_vx: true,
+                clipping: true,
+            },
+            QuirkPreset::Xochip => Quirks {
+                vf_reset: false,
+                memory_increment: true,
+                shift_vx: false,
+                jump_vx: false,
+                clipping: false,
+            },
+        }
+    }
+}
+
+impl Default for Quirks {
+    fn default() -> Quirks {
+        QuirkPreset::Chip8.quirks()
+    }
+}
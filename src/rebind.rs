@@ -0,0 +1,111 @@
+use crate::keyboard::HostInput;
+use crate::settings::Settings;
+use sdl2::keyboard::Scancode;
+
+// where the new bindings get saved once every key has one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveTo {
+    Global,
+    Rom,
+}
+
+// walks through keypad keys 0-F asking for a host key or gamepad control for
+// each, then asks where to save them. the emulator is paused the whole time
+pub struct Rebinding {
+    // what was picked for each keypad key so far, in order
+    chosen: Vec<HostInput>,
+    // what to tell the user, shown in the window title
+    pub message: String,
+}
+
+impl Rebinding {
+    pub fn new() -> Rebinding {
+        Rebinding {
+            chosen: Vec::new(),
+            message: prompt_for(0),
+        }
+    }
+
+    // None once all 16 keys are picked and it's waiting to be told where to save
+    pub fn current_key(&self) -> Option<usize> {
+        Some(self.chosen.len()).filter(|key| *key < 16)
+    }
+
+    // returns where to save once the user has chosen
+    pub fn offer(&mut self, input: HostInput) -> Option<SaveTo> {
+        let Some(key) = self.current_key() else {
+            return match input {
+                HostInput::Key(Scancode::G) => Some(SaveTo::Global),
+                HostInput::Key(Scancode::R) => Some(SaveTo::Rom),
+                _ => None,
+            };
+        };
+
+        if let Some(taken) = self.chosen.iter().position(|chosen| *chosen == input) {
+            self.message = format!(
+                "{} is already keypad {:X}, press something else for keypad {:X}",
+                input.name(),
+                taken,
+                key
+            );
+            return None;
+        }
+
+        self.chosen.push(input);
+        self.message = match self.current_key() {
+            Some(next) => prompt_for(next),
+            None => String::from("done: G saves for every rom, R for this rom, esc throws it away"),
+        };
+        None
+    }
+
+    // a keyboard key replaces whatever keys the keypad key had, a gamepad
+    // control is added next to the existing ones. either way the input stops
+    // pressing anything else
+    pub fn apply(&self, settings: &mut Settings) {
+        for (keypad_key, input) in self.chosen.iter().enumerate() {
+            let keypad_key = format!("{:X}", keypad_key);
+            let name = input.name();
+            match input {
+                HostInput::Key(_) => {
+                    for host_keys in settings.keys.values_mut() {
+                        host_keys.retain(|host_key| !host_key.eq_ignore_ascii_case(&name));
+                    }
+                    settings.keys.insert(keypad_key, vec![name]);
+                }
+                HostInput::Button(_) | HostInput::Axis(..) => {
+                    settings.gamepad.insert(name, keypad_key);
+                }
+                // never captured, the on-screen keys can't be rebound
+                HostInput::Touch(_) => {}
+            }
+        }
+    }
+}
+
+fn prompt_for(key: usize) -> String {
+    format!(
+        "rebinding: press a key or gamepad button for keypad {:X} (esc cancels)",
+        key
+    )
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use sdl2::controller::Button;
+
+    #[test]
+    fn rebinding_rejects_conflicts_test() {
+        let mut rebinding = Rebinding::new();
+        rebinding.offer(HostInput::Button(Button::A));
+        assert_eq!(None, rebinding.offer(HostInput::Button(Button::A)));
+        assert_eq!(Some(1), rebinding.current_key());
+
+        for _ in 1..16 {
+            rebinding.offer(HostInput::Button(Button::B));
+        }
+        assert_eq!(Some(2), rebinding.current_key());
+    }
+}
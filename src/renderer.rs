@@ -1,43 +1,394 @@
 extern crate sdl2;
-use crate::chip8::Chip8;
-use sdl2::pixels::Color;
+use crate::chip8::{Chip8, FONT};
+use crate::filters::{Filter, Frame};
+use crate::font;
+use crate::keyboard::{KeypadArea, SharedKeypadArea, KEYPAD_LAYOUT};
+use crate::palette::{Palette, BACKGROUND, FOREGROUND};
+use crate::phosphor::{blend, Phosphor};
+use sdl2::pixels::{Color, PixelFormatEnum};
 use sdl2::rect::Rect;
-use sdl2::render::WindowCanvas;
-use sdl2::video::Window;
+use sdl2::render::{BlendMode, Texture, WindowCanvas};
+use sdl2::video::{FullscreenType, Window};
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+const WIDTH: usize = 64;
+const HEIGHT: usize = 32;
+// the crt filters draw into a framebuffer this many times bigger than the
+// chip-8 screen at most, beyond that the cpu cost isn't worth it
+const MAX_FILTER_SCALE: usize = 16;
 
 pub struct Renderer {
     canvas: WindowCanvas,
+    // the filtered framebuffer, sdl scales it up the rest of the way when copying it to the
+    // viewport. unsafe_textures means sdl frees this along with the canvas instead of us tracking
+    // lifetimes
+    texture: Texture,
+    texture_size: (usize, usize),
+    palette: Palette,
+    phosphor: Phosphor,
+    filter: Filter,
+    // only scale by whole numbers, so every chip-8 pixel is the same size
+    integer_scaling: bool,
+    // the window's drawable size last time we drew, a resize means drawing again
+    output_size: (u32, u32),
+    // set when something other than vram changed what the screen should look like
+    needs_redraw: bool,
+    refresh_interval: Duration,
+    last_present: Instant,
+    // a 4x4 keypad drawn over the game, with these keys lit up
+    keypad_overlay: Option<[bool; 16]>,
+    // the clickable keypad, lit up with whatever is held
+    onscreen_keypad: bool,
+    keypad_state: [bool; 16],
+    keypad_area: SharedKeypadArea,
+    // text in the top left corner, e.g. the hud
+    hud: Option<Vec<String>>,
 }
 
 impl Renderer {
-    pub fn new(window: Window) -> Result<Renderer, String> {
+    pub fn new(
+        window: Window,
+        palette: Palette,
+        phosphor: Phosphor,
+        filter: Filter,
+        integer_scaling: bool,
+    ) -> Result<Renderer, String> {
+        // no point presenting faster than the monitor can show it
+        let refresh_rate = match window.display_mode() {
+            Ok(mode) if mode.refresh_rate > 0 => mode.refresh_rate as u64,
+            _ => 60,
+        };
+
         let canvas = window.into_canvas().build().map_err(|e| e.to_string())?;
-        Ok(Renderer { canvas })
+        let texture = Renderer::create_texture(&canvas, (WIDTH, HEIGHT))?;
+
+        Ok(Renderer {
+            canvas,
+            texture,
+            texture_size: (WIDTH, HEIGHT),
+            palette,
+            phosphor,
+            filter,
+            integer_scaling,
+            output_size: (0, 0),
+            needs_redraw: true,
+            refresh_interval: Duration::from_nanos(1_000_000_000 / refresh_rate),
+            last_present: Instant::now(),
+            keypad_overlay: None,
+            onscreen_keypad: false,
+            keypad_state: [false; 16],
+            keypad_area: Rc::new(Cell::new(None)),
+            hud: None,
+        })
     }
 
-    pub fn get_size(&self) -> Result<(u32, u32), String> {
-        self.canvas.output_size()
+    fn create_texture(
+        canvas: &WindowCanvas,
+        (width, height): (usize, usize),
+    ) -> Result<Texture, String> {
+        canvas
+            .texture_creator()
+            .create_texture_streaming(PixelFormatEnum::RGB24, width as u32, height as u32)
+            .map_err(|e| e.to_string())
     }
 
-    pub fn draw(&mut self, chip8: &mut Chip8) {
-        self.canvas.set_draw_color(Color::BLACK);
-        self.canvas.clear();
+    // the biggest 2:1 rectangle that fits in the window, centred. whatever is
+    // left over on the sides or top and bottom stays black
+    fn viewport(&self, (output_width, output_height): (u32, u32)) -> Rect {
+        let mut scale =
+            (output_width as f32 / WIDTH as f32).min(output_height as f32 / HEIGHT as f32);
+        if self.integer_scaling {
+            scale = scale.floor().max(1.0);
+        }
+
+        let width = (WIDTH as f32 * scale) as u32;
+        let height = (HEIGHT as f32 * scale) as u32;
+        Rect::new(
+            (output_width as i32 - width as i32) / 2,
+            (output_height as i32 - height as i32) / 2,
+            width,
+            height,
+        )
+    }
+
+    pub fn toggle_fullscreen(&mut self) -> Result<(), String> {
+        let window = self.canvas.window_mut();
+        let fullscreen = match window.fullscreen_state() {
+            FullscreenType::Off => FullscreenType::Desktop,
+            _ => FullscreenType::Off,
+        };
+        window.set_fullscreen(fullscreen)?;
+        self.needs_redraw = true;
+        Ok(())
+    }
+
+    pub fn set_palette(&mut self, palette: &Palette) {
+        self.palette = *palette;
+        self.needs_redraw = true;
+    }
+
+    pub fn set_filter(&mut self, filter: Filter) {
+        self.filter = filter;
+        self.needs_redraw = true;
+    }
+
+    pub fn set_keypad_overlay(&mut self, highlighted: Option<[bool; 16]>) {
+        if highlighted != self.keypad_overlay {
+            self.keypad_overlay = highlighted;
+            self.needs_redraw = true;
+        }
+    }
+
+    pub fn set_onscreen_keypad(&mut self, shown: bool) {
+        self.onscreen_keypad = shown;
+        if !shown {
+            self.keypad_area.set(None);
+        }
+        self.needs_redraw = true;
+    }
+
+    pub fn is_onscreen_keypad_shown(&self) -> bool {
+        self.onscreen_keypad
+    }
+
+    pub fn set_keypad_state(&mut self, state: &[bool; 16]) {
+        if self.onscreen_keypad && *state != self.keypad_state {
+            self.keypad_state = *state;
+            self.needs_redraw = true;
+        }
+    }
+
+    // the input side reads this to tell which key a click landed on
+    pub fn keypad_area(&self) -> SharedKeypadArea {
+        self.keypad_area.clone()
+    }
 
-        chip8.vram.iter_mut().for_each(|row| {
-            row.iter_mut().for_each(|pixel| {
-                let color = if pixel.on { Color::WHITE } else { Color::BLACK };
-                self.canvas.set_draw_color(color);
+    pub fn set_hud(&mut self, lines: Option<&[String]>) {
+        if lines != self.hud.as_deref() {
+            self.hud = lines.map(<[String]>::to_vec);
+            self.needs_redraw = true;
+        }
+    }
+
+    pub fn window(&self) -> &Window {
+        self.canvas.window()
+    }
+
+    pub fn set_title(&mut self, title: &str) {
+        // a title with a nul in it is the only way this fails, not worth stopping for
+        let _ = self.canvas.window_mut().set_title(title);
+    }
+
+    // below the game when there's room (a phone held upright), otherwise
+    // see-through in the bottom right corner of the game
+    fn onscreen_keypad_position(&self, viewport: Rect, (width, height): (u32, u32)) -> Rect {
+        let space_below = height as i32 - viewport.bottom();
+        let size = viewport.height() as i32 / 2;
+        if space_below >= size {
+            let size = (space_below * 9 / 10).min(width as i32 * 9 / 10);
+            return Rect::new(
+                (width as i32 - size) / 2,
+                viewport.bottom() + (space_below - size) / 2,
+                size as u32,
+                size as u32,
+            );
+        }
+        let margin = size / 16;
+        Rect::new(
+            viewport.right() - size - margin,
+            viewport.bottom() - size - margin,
+            size as u32,
+            size as u32,
+        )
+    }
+
+    // a keypad drawn with the chip-8 font, lit keys swap the foreground and
+    // background colours
+    fn draw_keypad(
+        &mut self,
+        area: Rect,
+        highlighted: &[bool; 16],
+        alpha: u8,
+    ) -> Result<(), String> {
+        let foreground = self.palette.colors[FOREGROUND];
+        let background = self.palette.colors[BACKGROUND];
+        let to_color = |(r, g, b): (u8, u8, u8)| Color::RGBA(r, g, b, alpha);
+
+        let cell = area.width() as i32 / 4;
+        let gap = (cell / 12).max(1);
+        let (left, top) = (area.x(), area.y());
+        // glyphs are 4x5, leave a border of about a glyph pixel around them
+        let dot = ((cell - gap * 2) / 7).max(1);
+
+        self.canvas.set_blend_mode(BlendMode::Blend);
+        // see-through keys let the game show between them instead of a frame
+        if alpha == u8::MAX {
+            self.canvas.set_draw_color(to_color(foreground));
+            self.canvas
+                .fill_rect(Rect::new(left, top, cell as u32 * 4, cell as u32 * 4))?;
+        }
+        for (position, key) in KEYPAD_LAYOUT.iter().enumerate() {
+            let x = left + (position % 4) as i32 * cell;
+            let y = top + (position / 4) as i32 * cell;
+            let (fill, ink) = match highlighted[*key] {
+                true => (foreground, background),
+                false => (background, foreground),
+            };
+            self.canvas.set_draw_color(to_color(fill));
+            self.canvas.fill_rect(Rect::new(
+                x + gap,
+                y + gap,
+                (cell - gap * 2) as u32,
+                (cell - gap * 2) as u32,
+            ))?;
+
+            self.canvas.set_draw_color(to_color(ink));
+            let glyph_left = x + (cell - dot * 4) / 2;
+            let glyph_top = y + (cell - dot * 5) / 2;
+            for (row, bits) in FONT[key * 5..key * 5 + 5].iter().enumerate() {
+                for column in 0..4 {
+                    if bits & (0x80 >> column) != 0 {
+                        self.canvas.fill_rect(Rect::new(
+                            glyph_left + column * dot,
+                            glyph_top + row as i32 * dot,
+                            dot as u32,
+                            dot as u32,
+                        ))?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
 
-                self.canvas.fill_rect(Rect::new(
-                    (pixel.x * chip8.vram_scale as u32) as i32,
-                    (pixel.y * chip8.vram_scale as u32) as i32,
-                    chip8.vram_scale as u32,
-                    chip8.vram_scale as u32,
-                ));
-            });
-        });
+    // white on a dark box so it's readable whatever the palette
+    fn draw_hud(&mut self, viewport: Rect, lines: &[String]) -> Result<(), String> {
+        let dot = (viewport.height() / 128).max(1) as i32;
+        let (left, top) = (viewport.x() + dot * 2, viewport.y() + dot * 2);
+        let line_height = (font::GLYPH_HEIGHT as i32 + 2) * dot;
+        let columns = lines.iter().map(|line| line.len()).max().unwrap_or(0);
 
+        self.canvas.set_blend_mode(BlendMode::Blend);
+        self.canvas.set_draw_color(Color::RGBA(0, 0, 0, 170));
+        self.canvas.fill_rect(Rect::new(
+            left - dot,
+            top - dot,
+            ((columns * font::ADVANCE) as i32 * dot + dot) as u32,
+            (lines.len() as i32 * line_height) as u32,
+        ))?;
+
+        let dots: Vec<Rect> = lines
+            .iter()
+            .enumerate()
+            .flat_map(|(row, line)| {
+                font::dots(line).into_iter().map(move |(x, y)| {
+                    Rect::new(
+                        left + x as i32 * dot,
+                        top + row as i32 * line_height + y as i32 * dot,
+                        dot as u32,
+                        dot as u32,
+                    )
+                })
+            })
+            .collect();
+        self.canvas.set_draw_color(Color::RGB(255, 255, 255));
+        self.canvas.fill_rects(&dots)
+    }
+
+    // hidpi windows have more pixels than window coordinates, clicks come in
+    // window coordinates
+    fn publish_keypad_area(&self, area: Rect, (output_width, output_height): (u32, u32)) {
+        let window_size = self.canvas.window().size();
+        let to_window_x = |x: i32| x * window_size.0 as i32 / output_width.max(1) as i32;
+        let to_window_y = |y: i32| y * window_size.1 as i32 / output_height.max(1) as i32;
+        self.keypad_area.set(Some(KeypadArea {
+            left: to_window_x(area.x()),
+            top: to_window_y(area.y()),
+            size: to_window_x(area.width() as i32) as u32,
+            window_size,
+        }));
+    }
+
+    // returns false when there was nothing new to show or the monitor isn't ready for it yet
+    pub fn draw(&mut self, chip8: &Chip8) -> Result<bool, String> {
+        // fading carries on every emulated frame, whether or not it gets presented
+        self.needs_redraw |= self.phosphor.update(&chip8.vram);
+        let output_size = self.canvas.output_size()?;
+        if output_size != self.output_size {
+            self.output_size = output_size;
+            self.needs_redraw = true;
+        }
+        if !self.needs_redraw {
+            return Ok(false);
+        }
+        // a millisecond of slack so a 60Hz emulator on a 60Hz monitor doesn't drop frames to jitter
+        if self.last_present.elapsed() + Duration::from_millis(1) < self.refresh_interval {
+            return Ok(false);
+        }
+
+        let foreground = self.palette.colors[FOREGROUND];
+        let background = self.palette.colors[BACKGROUND];
+        let pixels = (0..HEIGHT)
+            .flat_map(|y| (0..WIDTH).map(move |x| (x, y)))
+            .map(|(x, y)| blend(background, foreground, self.phosphor.brightness(x, y)))
+            .collect();
+        let viewport = self.viewport(output_size);
+        let factor = (viewport.width() as usize / WIDTH).clamp(1, MAX_FILTER_SCALE);
+        let frame = self
+            .filter
+            .apply(&Frame::new(WIDTH, HEIGHT, pixels), factor);
+
+        if self.texture_size != (frame.width, frame.height) {
+            let texture = Renderer::create_texture(&self.canvas, (frame.width, frame.height))?;
+            // safe, the old texture is never touched again
+            unsafe { std::mem::replace(&mut self.texture, texture).destroy() };
+            self.texture_size = (frame.width, frame.height);
+        }
+        self.texture
+            .with_lock(None, |buffer: &mut [u8], pitch: usize| {
+                for (y, row) in frame.pixels.chunks(frame.width).enumerate() {
+                    for (x, (r, g, b)) in row.iter().enumerate() {
+                        let offset = y * pitch + x * 3;
+                        buffer[offset..offset + 3].copy_from_slice(&[*r, *g, *b]);
+                    }
+                }
+            })?;
+
+        self.canvas.set_draw_color(Color::BLACK);
+        self.canvas.clear();
+        self.canvas.copy(&self.texture, None, viewport)?;
+        if self.onscreen_keypad {
+            let area = self.onscreen_keypad_position(viewport, output_size);
+            let alpha = if area.y() >= viewport.bottom() {
+                255
+            } else {
+                160
+            };
+            self.draw_keypad(area, &self.keypad_state.clone(), alpha)?;
+            self.publish_keypad_area(area, output_size);
+        }
+        if let Some(lines) = self.hud.take() {
+            let drawn = self.draw_hud(viewport, &lines);
+            self.hud = Some(lines);
+            drawn?;
+        }
+        if let Some(highlighted) = self.keypad_overlay {
+            // the rebinding prompt, in the middle of the game
+            let size = viewport.height() * 7 / 8;
+            let area = Rect::new(
+                viewport.x() + (viewport.width() - size) as i32 / 2,
+                viewport.y() + (viewport.height() - size) as i32 / 2,
+                size,
+                size,
+            );
+            self.draw_keypad(area, &highlighted, 255)?;
+        }
         self.canvas.present();
-        chip8.vram_changed = false;
+        self.last_present = Instant::now();
+        self.needs_redraw = false;
+
+        Ok(true)
     }
 }
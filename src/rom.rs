@@ -0,0 +1,31 @@
+use crate::octocart::{OctoOptions, Octocart};
+use std::fs;
+use std::path::Path;
+
+pub struct Rom {
+    pub program: Vec<u8>,
+    // only octocarts carry their own settings
+    pub octo_options: Option<OctoOptions>,
+}
+
+// reads a plain .ch8 binary, or unpacks the program from an octocart .gif
+pub fn read_rom(rom_file_path: &str) -> Result<Rom, String> {
+    let contents =
+        fs::read(rom_file_path).map_err(|e| format!("Error opening {}: {}", rom_file_path, e))?;
+
+    let is_gif = Path::new(rom_file_path)
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("gif"));
+    if !is_gif {
+        return Ok(Rom {
+            program: contents,
+            octo_options: None,
+        });
+    }
+
+    let octocart = Octocart::load(&contents).map_err(|e| format!("{}: {}", rom_file_path, e))?;
+    Ok(Rom {
+        program: octocart.program,
+        octo_options: Some(octocart.options),
+    })
+}
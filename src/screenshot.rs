@@ -0,0 +1,155 @@
+use crate::filters::Frame;
+use crate::palette::{Palette, BACKGROUND, FOREGROUND};
+use crate::pixel::Pixel;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScreenshotFormat {
+    // in the active palette, optionally scaled up
+    Png,
+    // black and white plain text pbm, 1 for lit pixels
+    Pbm,
+    // '#' for lit pixels and '.' for the rest
+    Ascii,
+}
+
+impl ScreenshotFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ScreenshotFormat::Png => "png",
+            ScreenshotFormat::Pbm => "pbm",
+            ScreenshotFormat::Ascii => "txt",
+        }
+    }
+}
+
+// encodes the screen in any of the formats, scale only matters for png
+pub fn capture(
+    vram: &[Vec<Pixel>],
+    palette: &Palette,
+    format: ScreenshotFormat,
+    scale: u32,
+) -> Result<Vec<u8>, String> {
+    match format {
+        ScreenshotFormat::Png => to_png(vram, palette, scale),
+        ScreenshotFormat::Pbm => Ok(to_pbm(vram).into_bytes()),
+        ScreenshotFormat::Ascii => Ok(to_ascii(vram).into_bytes()),
+    }
+}
+
+// the screen in the palette's colours, without any of the renderer's effects
+pub fn frame_from_vram(vram: &[Vec<Pixel>], palette: &Palette) -> Frame {
+    let pixels = vram
+        .iter()
+        .flatten()
+        .map(|pixel| match pixel.on {
+            true => palette.colors[FOREGROUND],
+            false => palette.colors[BACKGROUND],
+        })
+        .collect();
+    let width = vram.first().map_or(0, Vec::len);
+    Frame::new(width, vram.len(), pixels)
+}
+
+pub fn to_png(vram: &[Vec<Pixel>], palette: &Palette, scale: u32) -> Result<Vec<u8>, String> {
+    let frame = frame_from_vram(vram, palette).scale_nearest(scale.max(1) as usize);
+
+    let mut png_bytes = Vec::new();
+    {
+        let mut encoder =
+            png::Encoder::new(&mut png_bytes, frame.width as u32, frame.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+        let data: Vec<u8> = frame
+            .pixels
+            .iter()
+            .flat_map(|(r, g, b)| [*r, *g, *b])
+            .collect();
+        writer.write_image_data(&data).map_err(|e| e.to_string())?;
+    }
+    Ok(png_bytes)
+}
+
+pub fn to_pbm(vram: &[Vec<Pixel>]) -> String {
+    let width = vram.first().map_or(0, Vec::len);
+    let mut output = format!("P1\n{} {}\n", width, vram.len());
+    for row in vram {
+        let line: Vec<&str> = row
+            .iter()
+            .map(|pixel| if pixel.on { "1" } else { "0" })
+            .collect();
+        output += &line.join(" ");
+        output += "\n";
+    }
+    output
+}
+
+pub fn to_ascii(vram: &[Vec<Pixel>]) -> String {
+    vram.iter()
+        .map(|row| {
+            let mut line: String = row
+                .iter()
+                .map(|pixel| if pixel.on { '#' } else { '.' })
+                .collect();
+            line.push('\n');
+            line
+        })
+        .collect()
+}
+
+// <dir>/<rom name>-<date>-<time>.<extension>, creating dir if needed
+pub fn timestamped_path(dir: &Path, rom_name: &str, extension: &str) -> Result<PathBuf, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Error creating {}: {}", dir.display(), e))?;
+    let timestamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    let mut path = dir.join(format!("{}-{}.{}", rom_name, timestamp, extension));
+    // two in the same second get numbered instead of overwriting each other
+    let mut count = 2;
+    while path.exists() {
+        path = dir.join(format!(
+            "{}-{}-{}.{}",
+            rom_name, timestamp, count, extension
+        ));
+        count += 1;
+    }
+    Ok(path)
+}
+
+pub fn save(
+    dir: &Path,
+    rom_name: &str,
+    vram: &[Vec<Pixel>],
+    palette: &Palette,
+    format: ScreenshotFormat,
+    scale: u32,
+) -> Result<PathBuf, String> {
+    let bytes = capture(vram, palette, format, scale)?;
+    let path = timestamped_path(dir, rom_name, format.extension())?;
+    fs::write(&path, bytes).map_err(|e| format!("Error writing {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn text_screenshot_formats_test() {
+        let vram: Vec<Vec<Pixel>> = (0..2)
+            .map(|y| (0..3).map(|x| Pixel::new(x, y, x == y)).collect())
+            .collect();
+
+        assert_eq!("#..\n.#.\n", to_ascii(&vram));
+        assert_eq!("P1\n3 2\n1 0 0\n0 1 0\n", to_pbm(&vram));
+
+        let png_bytes = to_png(&vram, &Palette::default(), 2).unwrap();
+        let decoder = png::Decoder::new(std::io::Cursor::new(png_bytes));
+        let info = decoder.read_info().unwrap();
+        assert_eq!((6, 4), (info.info().width, info.info().height));
+    }
+}
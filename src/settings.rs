@@ -0,0 +1,185 @@
+use crate::beeper::Waveform;
+use crate::database::RomInfo;
+use crate::filters::Filter;
+use crate::frontend::Frontend;
+use crate::palette::{Palette, Theme};
+use crate::phosphor::Persistence;
+use crate::quirks::{QuirkPreset, Quirks};
+use crate::screenshot::ScreenshotFormat;
+use crate::video_recorder::VideoFormat;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+pub type Rgb = (u8, u8, u8);
+
+// the layout every chip-8 manual assumes, the keypad laid over the left side
+// of the keyboard. scancodes, so it's the same keys on azerty or dvorak
+//
+//   1 2 3 C      1 2 3 4
+//   4 5 6 D  ->  Q W E R
+//   7 8 9 E      A S D F
+//   A 0 B F      Z X C V
+const DEFAULT_KEYS: [(&str, &str); 16] = [
+    ("1", "1"),
+    ("2", "2"),
+    ("3", "3"),
+    ("C", "4"),
+    ("4", "Q"),
+    ("5", "W"),
+    ("6", "E"),
+    ("D", "R"),
+    ("7", "A"),
+    ("8", "S"),
+    ("9", "D"),
+    ("E", "F"),
+    ("A", "Z"),
+    ("0", "X"),
+    ("B", "C"),
+    ("F", "V"),
+];
+
+// everything the emulator needs to know before it starts. starts out as the
+// built-in defaults and gets overridden by the config file and the command line
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub frontend: Frontend,
+    // starting window size as a multiple of 64x32
+    pub scale: u32,
+    pub integer_scaling: bool,
+    pub fullscreen: bool,
+    // clickable keypad in the window, for mice and touch screens
+    pub onscreen_keypad: bool,
+    // fps, speed and registers over the game
+    pub hud: bool,
+    // open the debugger window and start paused
+    pub debugger: bool,
+    pub instructions_per_frame: u32,
+    // how many times faster fast-forward runs, and how many times slower slow motion
+    pub fast_forward: u32,
+    pub slow_motion: u32,
+    pub quirks: QuirkPreset,
+    // individual quirks that don't match any preset, e.g. from an octocart
+    pub custom_quirks: Option<Quirks>,
+    pub theme: Theme,
+    // starts out as the theme's colours, individual colours can be overridden
+    pub palette: Palette,
+    pub persistence: Persistence,
+    // percent of a pixel's brightness lost per frame in phosphor mode
+    pub phosphor_decay: u32,
+    pub filter: Filter,
+    pub audio: bool,
+    pub audio_frequency: u32,
+    // percent
+    pub volume: u32,
+    pub waveform: Waveform,
+    // keypad key (0-F) -> sdl scancode names, any of them presses it
+    pub keys: BTreeMap<String, Vec<String>>,
+    // gamepad control (sdl name, sticks with + or -) -> keypad key (0-F or none)
+    pub gamepad: BTreeMap<String, String>,
+    // percent of a stick's travel that's ignored
+    pub gamepad_deadzone: u32,
+    // host key or "pad:<control>" -> keypad key it presses over and over
+    pub turbo: BTreeMap<String, String>,
+    // turbo presses per second
+    pub turbo_rate: u32,
+    // host key or "pad:<control>" -> a sequence of presses, see macros.rs
+    pub macros: BTreeMap<String, String>,
+    // what the bundled rom database knows about the rom, if anything
+    pub rom_info: Option<RomInfo>,
+    // where per-rom changes made while running get saved
+    pub config_path: Option<PathBuf>,
+    pub rom_sha1: Option<String>,
+    // file name without the extension, for naming screenshots and recordings
+    pub rom_name: Option<String>,
+    pub seed: Option<u64>,
+    pub headless: bool,
+    // wav file to render the beeper into
+    pub record_audio: Option<PathBuf>,
+    // where screenshots and F9 recordings go
+    pub screenshot_dir: PathBuf,
+    pub screenshot_format: ScreenshotFormat,
+    // png screenshots only, 1 is the native 64x32
+    pub screenshot_scale: u32,
+    // video file (or - for stdout) recorded from the first frame
+    pub record_video: Option<PathBuf>,
+    // guessed from the file name when not set
+    pub video_format: Option<VideoFormat>,
+    pub video_scale: u32,
+    // input movies, see input_movie.rs
+    pub record_input: Option<PathBuf>,
+    pub play_input: Option<PathBuf>,
+    pub frames: Option<u64>,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            frontend: Frontend::Sdl,
+            scale: 10,
+            integer_scaling: false,
+            fullscreen: false,
+            onscreen_keypad: false,
+            hud: false,
+            debugger: false,
+            instructions_per_frame: 11,
+            fast_forward: 4,
+            slow_motion: 4,
+            quirks: QuirkPreset::Chip8,
+            custom_quirks: None,
+            theme: Theme::Classic,
+            palette: Theme::Classic.palette(),
+            persistence: Persistence::Off,
+            phosphor_decay: 30,
+            filter: Filter::None,
+            audio: true,
+            audio_frequency: 440,
+            volume: 50,
+            waveform: Waveform::Square,
+            keys: DEFAULT_KEYS
+                .iter()
+                .map(|(keypad_key, host_key)| (keypad_key.to_string(), vec![host_key.to_string()]))
+                .collect(),
+            // the usual 2/4/6/8 directions, with 5 as the button
+            gamepad: [
+                ("dpup", "2"),
+                ("dpdown", "8"),
+                ("dpleft", "4"),
+                ("dpright", "6"),
+                ("lefty-", "2"),
+                ("lefty+", "8"),
+                ("leftx-", "4"),
+                ("leftx+", "6"),
+                ("a", "5"),
+            ]
+            .iter()
+            .map(|(control, keypad_key)| (control.to_string(), keypad_key.to_string()))
+            .collect(),
+            gamepad_deadzone: 25,
+            turbo: BTreeMap::new(),
+            turbo_rate: 10,
+            macros: BTreeMap::new(),
+            rom_info: None,
+            config_path: None,
+            rom_sha1: None,
+            rom_name: None,
+            seed: None,
+            headless: false,
+            record_audio: None,
+            screenshot_dir: PathBuf::from("screenshots"),
+            screenshot_format: ScreenshotFormat::Png,
+            screenshot_scale: 1,
+            record_video: None,
+            video_format: None,
+            video_scale: 4,
+            record_input: None,
+            play_input: None,
+            frames: None,
+        }
+    }
+}
+
+impl Settings {
+    pub fn effective_quirks(&self) -> Quirks {
+        self.custom_quirks.unwrap_or(self.quirks.quirks())
+    }
+}
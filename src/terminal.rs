@@ -0,0 +1,324 @@
+use crate::chip8::Chip8;
+use crate::frontend::{Hotkey, InputSource, VideoSink};
+use crate::keyboard::Keyboard;
+use crate::palette::{Palette, BACKGROUND, FOREGROUND};
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{
+    self, Event, KeyCode, KeyEventKind, KeyModifiers, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Stdout, Write};
+use std::time::{Duration, Instant};
+
+// without key release events a tap has to count as held for a while. the
+// first press covers the terminal's key repeat delay, repeats after that only
+// need to bridge the gap to the next repeat
+const FIRST_PRESS_FRAMES: u8 = 30;
+const REPEAT_FRAMES: u8 = 6;
+
+const STATUS_INTERVAL: Duration = Duration::from_millis(250);
+
+// draws the screen with half block characters, the top pixel of each cell is
+// the foreground colour and the bottom pixel the background, so 64x32 pixels
+// fit in 64x16 characters. owns the terminal: raw mode and the alternate
+// screen are switched on here and put back when it's dropped
+pub struct TerminalVideo {
+    out: Stdout,
+    palette: Palette,
+    needs_redraw: bool,
+    keyboard_enhanced: bool,
+    frames: u32,
+    fps: u32,
+    fps_since: Instant,
+    last_status: Instant,
+}
+
+impl TerminalVideo {
+    pub fn new(palette: Palette) -> Result<TerminalVideo, String> {
+        let mut out = io::stdout();
+        terminal::enable_raw_mode().map_err(|e| e.to_string())?;
+        // there's no TerminalVideo to drop yet, so raw mode is undone by hand
+        if let Err(e) = execute!(out, EnterAlternateScreen, Hide, Clear(ClearType::All)) {
+            let _ = execute!(out, Show, LeaveAlternateScreen);
+            let _ = terminal::disable_raw_mode();
+            return Err(e.to_string());
+        }
+
+        // terminals that support it will tell us when keys are let go
+        let keyboard_enhanced = terminal::supports_keyboard_enhancement().unwrap_or(false)
+            && execute!(
+                out,
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+            )
+            .is_ok();
+
+        Ok(TerminalVideo {
+            out,
+            palette,
+            needs_redraw: true,
+            keyboard_enhanced,
+            frames: 0,
+            fps: 0,
+            fps_since: Instant::now(),
+            last_status: Instant::now(),
+        })
+    }
+
+    fn draw_screen(&mut self, chip8: &Chip8) -> io::Result<()> {
+        let color = |on: bool, palette: &Palette| {
+            let (r, g, b) = palette.colors[if on { FOREGROUND } else { BACKGROUND }];
+            Color::Rgb { r, g, b }
+        };
+
+        for (row, pair) in chip8.vram.chunks(2).enumerate() {
+            queue!(self.out, MoveTo(0, row as u16))?;
+            // only send colour changes, this goes over ssh 60 times a second
+            let mut current = None;
+            for (top, bottom) in pair[0].iter().zip(&pair[1]) {
+                if current != Some((top.on, bottom.on)) {
+                    queue!(
+                        self.out,
+                        SetForegroundColor(color(top.on, &self.palette)),
+                        SetBackgroundColor(color(bottom.on, &self.palette))
+                    )?;
+                    current = Some((top.on, bottom.on));
+                }
+                queue!(self.out, Print('▀'))?;
+            }
+            queue!(self.out, ResetColor)?;
+        }
+        Ok(())
+    }
+
+    fn draw_status(&mut self, chip8: &Chip8) -> io::Result<()> {
+        let registers: Vec<String> = chip8
+            .get_registers()
+            .iter()
+            .map(|value| format!("{:02X}", value))
+            .collect();
+        queue!(
+            self.out,
+            MoveTo(0, 16),
+            Print(format!(
+                "PC {:03X}  I {:03X}  {} fps  (esc quits)",
+                chip8.get_program_counter(),
+                chip8.get_index_register(),
+                self.fps
+            )),
+            Clear(ClearType::UntilNewLine),
+            MoveTo(0, 17),
+            Print(format!("V0-VF {}", registers.join(" "))),
+            Clear(ClearType::UntilNewLine)
+        )
+    }
+}
+
+impl VideoSink for TerminalVideo {
+    fn draw(&mut self, chip8: &Chip8) -> Result<bool, String> {
+        self.frames += 1;
+        if self.fps_since.elapsed() >= Duration::from_secs(1) {
+            self.fps = self.frames;
+            self.frames = 0;
+            self.fps_since = Instant::now();
+        }
+
+        let screen_changed = chip8.vram_changed || self.needs_redraw;
+        if screen_changed {
+            self.draw_screen(chip8).map_err(|e| e.to_string())?;
+            self.needs_redraw = false;
+        }
+        if self.last_status.elapsed() >= STATUS_INTERVAL {
+            self.draw_status(chip8).map_err(|e| e.to_string())?;
+            self.last_status = Instant::now();
+        }
+        self.out.flush().map_err(|e| e.to_string())?;
+
+        Ok(screen_changed)
+    }
+
+    fn set_palette(&mut self, palette: &Palette) {
+        self.palette = *palette;
+        self.needs_redraw = true;
+    }
+}
+
+impl Drop for TerminalVideo {
+    fn drop(&mut self) {
+        if self.keyboard_enhanced {
+            let _ = execute!(self.out, PopKeyboardEnhancementFlags);
+        }
+        let _ = execute!(self.out, ResetColor, Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+// keypad input from the terminal. only works together with TerminalVideo,
+// which puts the terminal in raw mode
+pub struct TerminalInput {
+    bindings: HashMap<KeyCode, usize>,
+    // frames each keypad key stays held for
+    held: [u8; 16],
+    // once a release event shows up the terminal can be trusted to send them
+    releases_reported: bool,
+}
+
+impl TerminalInput {
+    // same key names as the sdl bindings, single characters and a few named keys.
+    // terminals only send characters, so here it's the printed key that counts
+    // rather than its position
+    pub fn with_bindings(keys: &BTreeMap<String, Vec<String>>) -> Result<TerminalInput, String> {
+        let mut bindings = HashMap::new();
+        for (keypad_key, host_key) in keys.iter().flat_map(|(keypad_key, host_keys)| {
+            host_keys.iter().map(move |host_key| (keypad_key, host_key))
+        }) {
+            let keypad_key = usize::from_str_radix(keypad_key, 16)
+                .ok()
+                .filter(|key| *key < 16)
+                .ok_or_else(|| format!("'{}' is not a keypad key (0-F)", keypad_key))?;
+            let key_code = match host_key.to_lowercase().as_str() {
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "space" => KeyCode::Char(' '),
+                "return" => KeyCode::Enter,
+                "tab" => KeyCode::Tab,
+                "backspace" => KeyCode::Backspace,
+                name if name.chars().count() == 1 => KeyCode::Char(name.chars().next().unwrap()),
+                _ => return Err(format!("can't use key '{}' in the terminal", host_key)),
+            };
+            bindings.insert(key_code, keypad_key);
+        }
+
+        Ok(TerminalInput {
+            bindings,
+            held: [0; 16],
+            releases_reported: false,
+        })
+    }
+
+    fn handle_key(&mut self, code: KeyCode, kind: KeyEventKind) -> Option<Hotkey> {
+        let code = match code {
+            KeyCode::Char(c) => KeyCode::Char(c.to_ascii_lowercase()),
+            code => code,
+        };
+        match (code, kind) {
+            (_, KeyEventKind::Release) => {
+                self.releases_reported = true;
+                if let Some(key) = self.bindings.get(&code) {
+                    self.held[*key] = 0;
+                }
+                None
+            }
+            (KeyCode::Esc, _) => Some(Hotkey::Quit),
+            (KeyCode::F(2), KeyEventKind::Press) => Some(Hotkey::NextTheme),
+            (KeyCode::F(5), KeyEventKind::Press) => Some(Hotkey::Pause),
+            (KeyCode::F(6), _) => Some(Hotkey::Step),
+            (KeyCode::F(10), _) => Some(Hotkey::FrameAdvance),
+            // a keypad key bound to one of these wins over the hotkey
+            (KeyCode::Backspace, KeyEventKind::Press) if !self.bindings.contains_key(&code) => {
+                Some(Hotkey::Reset)
+            }
+            (KeyCode::Tab, KeyEventKind::Press) if !self.bindings.contains_key(&code) => {
+                Some(Hotkey::ToggleFastForward)
+            }
+            (KeyCode::Char('`'), KeyEventKind::Press) if !self.bindings.contains_key(&code) => {
+                Some(Hotkey::ToggleSlowMotion)
+            }
+            (KeyCode::F(9), KeyEventKind::Press) => Some(Hotkey::ToggleRecording),
+            (KeyCode::F(12), KeyEventKind::Press) => Some(Hotkey::Screenshot),
+            _ => {
+                if let Some(key) = self.bindings.get(&code) {
+                    self.held[*key] = match (self.releases_reported, self.held[*key]) {
+                        (true, _) => 1,
+                        (false, 0) => FIRST_PRESS_FRAMES,
+                        (false, _) => REPEAT_FRAMES,
+                    };
+                }
+                None
+            }
+        }
+    }
+}
+
+impl InputSource for TerminalInput {
+    fn poll(&mut self, keyboard: &mut Keyboard) -> Vec<Hotkey> {
+        if !self.releases_reported {
+            self.held
+                .iter_mut()
+                .for_each(|frames| *frames = frames.saturating_sub(1));
+        }
+
+        let mut hotkeys = Vec::new();
+        while event::poll(Duration::ZERO).unwrap_or(false) {
+            let Ok(Event::Key(key_event)) = event::read() else {
+                continue;
+            };
+            // raw mode swallows ctrl+c, so it has to be handled here
+            if key_event.modifiers.contains(KeyModifiers::CONTROL)
+                && key_event.code == KeyCode::Char('c')
+            {
+                hotkeys.push(Hotkey::Quit);
+                continue;
+            }
+            hotkeys.extend(self.handle_key(key_event.code, key_event.kind));
+        }
+
+        keyboard.set_state(self.held.map(|frames| frames > 0));
+        hotkeys
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn terminal_key_taps_and_releases_test() {
+        let keys = BTreeMap::from([(String::from("5"), vec![String::from("W")])]);
+        let mut input = TerminalInput::with_bindings(&keys).unwrap();
+
+        // no release events: a tap holds the key long enough to cover the repeat delay
+        assert_eq!(
+            None,
+            input.handle_key(KeyCode::Char('W'), KeyEventKind::Press)
+        );
+        assert_eq!(FIRST_PRESS_FRAMES, input.held[5]);
+        input.handle_key(KeyCode::Char('w'), KeyEventKind::Press);
+        assert_eq!(REPEAT_FRAMES, input.held[5]);
+
+        // a terminal that reports releases gets exact presses
+        input.handle_key(KeyCode::Char('w'), KeyEventKind::Release);
+        assert_eq!(0, input.held[5]);
+        input.handle_key(KeyCode::Char('w'), KeyEventKind::Press);
+        assert_eq!(1, input.held[5]);
+
+        assert_eq!(
+            Some(Hotkey::Quit),
+            input.handle_key(KeyCode::Esc, KeyEventKind::Press)
+        );
+        assert!(TerminalInput::with_bindings(&BTreeMap::from([(
+            String::from("1"),
+            vec![String::from("Keypad 1")]
+        )]))
+        .is_err());
+    }
+
+    #[test]
+    fn bound_keys_win_over_hotkeys_test() {
+        let keys = BTreeMap::from([(String::from("A"), vec![String::from("Tab")])]);
+        let mut input = TerminalInput::with_bindings(&keys).unwrap();
+
+        assert_eq!(None, input.handle_key(KeyCode::Tab, KeyEventKind::Press));
+        assert_eq!(FIRST_PRESS_FRAMES, input.held[0xA]);
+        assert_eq!(
+            Some(Hotkey::Reset),
+            input.handle_key(KeyCode::Backspace, KeyEventKind::Press)
+        );
+    }
+}
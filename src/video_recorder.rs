@@ -0,0 +1,301 @@
+use crate::filters::Frame;
+use crate::palette::Palette;
+use crate::pixel::Pixel;
+use crate::screenshot::frame_from_vram;
+use clap::ValueEnum;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+const WIDTH: usize = 64;
+const HEIGHT: usize = 32;
+// browsers play anything shorter than this as 10 centiseconds, so a game that
+// flickers every frame would crawl along
+const MIN_GIF_DELAY: u64 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum VideoFormat {
+    // animated gif, for sharing. gif delays can't go below 2/100s in browsers,
+    // so anything faster than 30fps has frames merged away
+    Gif,
+    // yuv4mpeg2 (4:4:4), which ffmpeg and most encoders read straight from a pipe
+    Y4m,
+    // bare rgb24 frames, e.g. ffmpeg -f rawvideo -pix_fmt rgb24 -s 64x32 -r 60 -i -
+    Rgb,
+}
+
+impl VideoFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            VideoFormat::Gif => "gif",
+            VideoFormat::Y4m => "y4m",
+            VideoFormat::Rgb => "rgb",
+        }
+    }
+
+    fn from_path(path: &Path) -> Option<VideoFormat> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "gif" => Some(VideoFormat::Gif),
+            "y4m" => Some(VideoFormat::Y4m),
+            "rgb" | "raw" => Some(VideoFormat::Rgb),
+            _ => None,
+        }
+    }
+}
+
+enum VideoOutput {
+    Gif(gif::Encoder<Box<dyn Write>>),
+    Y4m(Box<dyn Write>),
+    Rgb(Box<dyn Write>),
+}
+
+// records one video frame per emulated frame, so the video always runs at
+// exactly 60fps no matter how fast or unevenly the host ran the emulator
+pub struct VideoRecorder {
+    output: VideoOutput,
+    scale: usize,
+    frames: u64,
+    // gif only: the picture currently showing and the frame it appeared on.
+    // it's written out once it changes, so a still screen is a single gif frame
+    pending: Option<(Frame, u64)>,
+}
+
+impl VideoRecorder {
+    // "-" writes to stdout. without a format it's guessed from the extension,
+    // stdout gets y4m
+    pub fn create(
+        path: &Path,
+        format: Option<VideoFormat>,
+        scale: u32,
+    ) -> Result<VideoRecorder, String> {
+        let to_stdout = path == Path::new("-");
+        let format = match (format, to_stdout) {
+            (Some(format), _) => format,
+            (None, true) => VideoFormat::Y4m,
+            (None, false) => VideoFormat::from_path(path).ok_or_else(|| {
+                format!(
+                    "can't tell the video format of {}, use gif, y4m or rgb",
+                    path.display()
+                )
+            })?,
+        };
+
+        let mut writer: Box<dyn Write> = if to_stdout {
+            Box::new(BufWriter::new(io::stdout()))
+        } else {
+            let file = File::create(path)
+                .map_err(|e| format!("Error creating {}: {}", path.display(), e))?;
+            Box::new(BufWriter::new(file))
+        };
+
+        let scale = scale.max(1) as usize;
+        let (width, height) = (WIDTH * scale, HEIGHT * scale);
+        let output = match format {
+            VideoFormat::Gif => {
+                let mut encoder = gif::Encoder::new(writer, width as u16, height as u16, &[])
+                    .map_err(|e| e.to_string())?;
+                encoder
+                    .set_repeat(gif::Repeat::Infinite)
+                    .map_err(|e| e.to_string())?;
+                VideoOutput::Gif(encoder)
+            }
+            VideoFormat::Y4m => {
+                writeln!(
+                    writer,
+                    "YUV4MPEG2 W{} H{} F60:1 Ip A1:1 C444",
+                    width, height
+                )
+                .map_err(|e| e.to_string())?;
+                VideoOutput::Y4m(writer)
+            }
+            VideoFormat::Rgb => VideoOutput::Rgb(writer),
+        };
+
+        Ok(VideoRecorder {
+            output,
+            scale,
+            frames: 0,
+            pending: None,
+        })
+    }
+
+    pub fn record(&mut self, vram: &[Vec<Pixel>], palette: &Palette) -> Result<(), String> {
+        let frame = frame_from_vram(vram, palette).scale_nearest(self.scale);
+
+        match &mut self.output {
+            VideoOutput::Gif(encoder) => match self.pending.take() {
+                // a picture too short to show is replaced by the next one,
+                // which takes over its start time so the total stays exact
+                Some((shown, since))
+                    if shown != frame
+                        && centiseconds(self.frames) - centiseconds(since) < MIN_GIF_DELAY =>
+                {
+                    self.pending = Some((frame, since));
+                }
+                Some((shown, since)) if shown != frame => {
+                    write_gif_frame(encoder, &shown, since, self.frames)?;
+                    self.pending = Some((frame, self.frames));
+                }
+                Some(pending) => self.pending = Some(pending),
+                None => self.pending = Some((frame, self.frames)),
+            },
+            VideoOutput::Y4m(writer) => {
+                let (y, u, v) = to_yuv444(&frame);
+                writer
+                    .write_all(b"FRAME\n")
+                    .and_then(|_| writer.write_all(&y))
+                    .and_then(|_| writer.write_all(&u))
+                    .and_then(|_| writer.write_all(&v))
+                    .map_err(|e| e.to_string())?;
+            }
+            VideoOutput::Rgb(writer) => {
+                let bytes: Vec<u8> = frame
+                    .pixels
+                    .iter()
+                    .flat_map(|(r, g, b)| [*r, *g, *b])
+                    .collect();
+                writer.write_all(&bytes).map_err(|e| e.to_string())?;
+            }
+        }
+
+        self.frames += 1;
+        Ok(())
+    }
+
+    // writes out whatever is still buffered, the gif isn't valid without this
+    pub fn finish(mut self) -> Result<(), String> {
+        let mut writer = match self.output {
+            VideoOutput::Gif(mut encoder) => {
+                if let Some((shown, since)) = self.pending.take() {
+                    write_gif_frame(&mut encoder, &shown, since, self.frames)?;
+                }
+                encoder.into_inner().map_err(|e| e.to_string())?
+            }
+            VideoOutput::Y4m(writer) | VideoOutput::Rgb(writer) => writer,
+        };
+        writer.flush().map_err(|e| e.to_string())
+    }
+}
+
+// gif delays are in hundredths of a second, which 60fps doesn't divide into.
+// rounding each frame's start time instead of its length keeps the total exact
+fn centiseconds(frame: u64) -> u64 {
+    (frame * 100 + 30) / 60
+}
+
+fn write_gif_frame(
+    encoder: &mut gif::Encoder<Box<dyn Write>>,
+    frame: &Frame,
+    start: u64,
+    end: u64,
+) -> Result<(), String> {
+    let mut colors: Vec<(u8, u8, u8)> = Vec::new();
+    let indices: Vec<u8> = frame
+        .pixels
+        .iter()
+        .map(|color| {
+            let index = colors
+                .iter()
+                .position(|known| known == color)
+                .unwrap_or_else(|| {
+                    colors.push(*color);
+                    colors.len() - 1
+                });
+            index as u8
+        })
+        .collect();
+    // gif palettes need at least two entries
+    colors.resize(colors.len().max(2), (0, 0, 0));
+    let palette: Vec<u8> = colors.iter().flat_map(|(r, g, b)| [*r, *g, *b]).collect();
+
+    let mut gif_frame = gif::Frame::from_palette_pixels(
+        frame.width as u16,
+        frame.height as u16,
+        indices,
+        palette,
+        None,
+    );
+    // only the last frame can come out shorter, it's stretched a little rather
+    // than a lot
+    gif_frame.delay =
+        (centiseconds(end) - centiseconds(start)).clamp(MIN_GIF_DELAY, u16::MAX as u64) as u16;
+    encoder.write_frame(&gif_frame).map_err(|e| e.to_string())
+}
+
+// bt.601 limited range, what y4m players expect
+fn to_yuv444(frame: &Frame) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+    let mut y_plane = Vec::with_capacity(frame.pixels.len());
+    let mut u_plane = Vec::with_capacity(frame.pixels.len());
+    let mut v_plane = Vec::with_capacity(frame.pixels.len());
+    for (r, g, b) in &frame.pixels {
+        let (r, g, b) = (*r as i32, *g as i32, *b as i32);
+        y_plane.push((((66 * r + 129 * g + 25 * b + 128) >> 8) + 16) as u8);
+        u_plane.push((((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128) as u8);
+        v_plane.push((((112 * r - 94 * g - 18 * b + 128) >> 8) + 128) as u8);
+    }
+    (y_plane, u_plane, v_plane)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn vram_with(on: bool) -> Vec<Vec<Pixel>> {
+        (0..HEIGHT as u32)
+            .map(|y| {
+                (0..WIDTH as u32)
+                    .map(|x| Pixel::new(x, y, on && x == y))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn gif_recording_keeps_60fps_timing_test() {
+        let path = std::env::temp_dir().join("chip8_gif_recording_test.gif");
+        let mut recorder = VideoRecorder::create(&path, None, 1).unwrap();
+        let palette = Palette::default();
+        // 3 frames lit then 57 dark, one second in total
+        for frame in 0..60 {
+            recorder.record(&vram_with(frame < 3), &palette).unwrap();
+        }
+        recorder.finish().unwrap();
+
+        let mut decoder = gif::DecodeOptions::new()
+            .read_info(File::open(&path).unwrap())
+            .unwrap();
+        let mut delays = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            delays.push(frame.delay);
+        }
+
+        assert_eq!(vec![5, 95], delays);
+        assert_eq!(100, centiseconds(60));
+    }
+
+    #[test]
+    fn gif_flicker_never_drops_below_min_delay_test() {
+        let path = std::env::temp_dir().join("chip8_gif_flicker_test.gif");
+        let mut recorder = VideoRecorder::create(&path, None, 1).unwrap();
+        let palette = Palette::default();
+        // a different picture every frame for a second
+        for frame in 0..60 {
+            recorder
+                .record(&vram_with(frame % 2 == 0), &palette)
+                .unwrap();
+        }
+        recorder.finish().unwrap();
+
+        let mut decoder = gif::DecodeOptions::new()
+            .read_info(File::open(&path).unwrap())
+            .unwrap();
+        let mut delays = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            delays.push(frame.delay as u64);
+        }
+
+        assert!(delays.iter().all(|delay| *delay >= MIN_GIF_DELAY));
+        assert_eq!(100, delays.iter().sum::<u64>());
+    }
+}
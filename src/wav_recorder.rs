@@ -0,0 +1,113 @@
+use crate::beeper::Beeper;
+use crate::frontend::AudioSink;
+use crate::settings::Settings;
+use hound::{SampleFormat, WavSpec, WavWriter};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+const SAMPLE_RATE: u32 = 44100;
+// 44100 / 60 divides evenly, so every frame is exactly the same length
+const SAMPLES_PER_FRAME: usize = (SAMPLE_RATE / 60) as usize;
+
+// renders the beeper into a wav file, one frame's worth of samples every time
+// the emulator ticks the sound. nothing here looks at the wall clock, so the
+// same rom and input always produce the same file
+pub struct WavRecorder {
+    writer: Option<WavWriter<BufWriter<File>>>,
+    beeper: Beeper,
+    tone_on: Arc<AtomicBool>,
+}
+
+impl WavRecorder {
+    pub fn create(path: &Path, settings: &Settings) -> Result<WavRecorder, String> {
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate: SAMPLE_RATE,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        let writer = WavWriter::create(path, spec)
+            .map_err(|e| format!("Error creating {}: {}", path.display(), e))?;
+
+        let tone_on = Arc::new(AtomicBool::new(false));
+        let beeper = Beeper::new(
+            tone_on.clone(),
+            settings.audio_frequency as f32,
+            settings.volume as f32 / 100.0,
+            settings.waveform,
+            SAMPLE_RATE,
+        );
+
+        Ok(WavRecorder {
+            writer: Some(writer),
+            beeper,
+            tone_on,
+        })
+    }
+
+    fn render_frame(&mut self, tone_on: bool) {
+        self.tone_on.store(tone_on, Ordering::Relaxed);
+
+        let Some(writer) = self.writer.as_mut() else {
+            return;
+        };
+        for sample in self.beeper.by_ref().take(SAMPLES_PER_FRAME) {
+            if let Err(e) = writer.write_sample((sample * i16::MAX as f32) as i16) {
+                // give up on the recording rather than the whole emulator
+                eprintln!("warning: stopped recording audio: {}", e);
+                self.writer = None;
+                return;
+            }
+        }
+    }
+}
+
+impl AudioSink for WavRecorder {
+    fn play(&mut self) {
+        self.render_frame(true);
+    }
+
+    fn stop(&mut self) {
+        self.render_frame(false);
+    }
+}
+
+impl Drop for WavRecorder {
+    fn drop(&mut self) {
+        if let Some(writer) = self.writer.take() {
+            if let Err(e) = writer.finalize() {
+                eprintln!("warning: could not finish the audio recording: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn record_frames_to_wav_test() {
+        let path = std::env::temp_dir().join("chip8_record_frames_to_wav_test.wav");
+        {
+            let mut recorder = WavRecorder::create(&path, &Settings::default()).unwrap();
+            recorder.play();
+            recorder.play();
+            recorder.stop();
+        }
+
+        let mut reader = hound::WavReader::open(&path).unwrap();
+        let samples: Vec<i16> = reader.samples::<i16>().map(|s| s.unwrap()).collect();
+
+        assert_eq!(3 * SAMPLES_PER_FRAME, samples.len());
+        assert!(samples[..2 * SAMPLES_PER_FRAME].iter().any(|s| *s != 0));
+        // the release is only 5ms, the rest of the last frame is silence
+        assert!(samples[2 * SAMPLES_PER_FRAME + 300..]
+            .iter()
+            .all(|s| *s == 0));
+    }
+}